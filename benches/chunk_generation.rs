@@ -0,0 +1,128 @@
+use bevy::prelude::*;
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
+use procjam_2023::coords;
+use procjam_2023::game_map::{
+    sample_chunk, ChunkData, ChunkStreamingSettings, GameMapPlugin, CHUNK_SIZE,
+};
+use procjam_2023::game_ui::RegenerateMapEvent;
+use procjam_2023::noise_generator::NoiseGenerator;
+use procjam_2023::tile_deltas::TileDeltas;
+use procjam_2023::view_mode::ViewMode;
+
+// Somewhere away from the origin, where the noise has no special cases.
+const CHUNK_POS: IVec2 = IVec2::new(7, -3);
+// A 1080p window at the default zoom.
+const VIEW_SIZE: Vec2 = Vec2::new(1920.0, 1080.0);
+// Gives up on streaming instead of spinning forever if chunks stop arriving.
+const MAX_FRAMES: usize = 10_000;
+
+fn tile_sampling(c: &mut Criterion) {
+    let noise = NoiseGenerator::default();
+    let mut x = 0;
+    c.bench_function("get_tile_data", |b| {
+        b.iter(|| {
+            x += 1;
+            noise.get_tile_data(black_box(IVec2::new(x, -x)))
+        })
+    });
+}
+
+fn grid_sampling(c: &mut Criterion) {
+    let noise = NoiseGenerator::default();
+    let origin = coords::chunk_origin(CHUNK_POS);
+    let mut group = c.benchmark_group("sample chunk layers");
+    group.bench_function("per tile", |b| {
+        b.iter(|| {
+            for y in 0..CHUNK_SIZE.y as i32 {
+                for x in 0..CHUNK_SIZE.x as i32 {
+                    black_box(noise.get_tile_data(origin + IVec2::new(x, y)));
+                }
+            }
+        })
+    });
+    group.bench_function("batch", |b| {
+        b.iter(|| noise.get_tile_data_grid(black_box(origin), CHUNK_SIZE))
+    });
+    group.finish();
+}
+
+fn chunk_generation(c: &mut Criterion) {
+    let noise = NoiseGenerator::default();
+    let deltas = TileDeltas::default();
+    let mut group = c.benchmark_group("sample_chunk");
+    for view_mode in ViewMode::ALL {
+        group.bench_function(view_mode.name(), |b| {
+            b.iter(|| sample_chunk(&noise, &deltas, view_mode, black_box(CHUNK_POS)))
+        });
+    }
+    group.finish();
+}
+
+fn biome_classification(c: &mut Criterion) {
+    let samples = sample_chunk(
+        &NoiseGenerator::default(),
+        &TileDeltas::default(),
+        ViewMode::TileTypes,
+        CHUNK_POS,
+    );
+    c.bench_function("get_tile_type (chunk)", |b| {
+        b.iter(|| {
+            for tile_data in samples.tiles.tiles() {
+                black_box(tile_data.get_tile_type());
+            }
+        })
+    });
+}
+
+// The map plugin without a window or renderer, looking at the origin.
+fn headless_app() -> App {
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, AssetPlugin::default()))
+        .init_asset::<Image>()
+        .add_event::<RegenerateMapEvent>()
+        .add_plugins(GameMapPlugin)
+        // Measures the whole work instead of how it's spread across frames.
+        .insert_resource(ChunkStreamingSettings {
+            frame_budget_ms: 1000.0,
+            despawn_margin: 2,
+            cache_size: 0,
+        });
+
+    // Normally kept up to date by the render plugin from the window size.
+    let mut camera = Camera2dBundle::default();
+    camera.projection.area = Rect::from_center_size(Vec2::ZERO, VIEW_SIZE);
+    app.world.spawn(camera);
+    app
+}
+
+fn chunk_streaming(c: &mut Criterion) {
+    let (min, max) = coords::chunks_overlapping(Rect::from_center_size(Vec2::ZERO, VIEW_SIZE));
+    let visible_chunks = ((max.x - min.x + 1) * (max.y - min.y + 1)) as usize;
+
+    c.bench_function("stream visible chunks", |b| {
+        b.iter_batched(
+            headless_app,
+            |mut app| {
+                for _ in 0..MAX_FRAMES {
+                    app.update();
+                    let spawned = app.world.query::<&ChunkData>().iter(&app.world).count();
+                    if spawned == visible_chunks {
+                        return app;
+                    }
+                }
+                panic!("chunks didn't finish streaming within {MAX_FRAMES} frames");
+            },
+            BatchSize::PerIteration,
+        )
+    });
+}
+
+criterion_group!(
+    benches,
+    tile_sampling,
+    grid_sampling,
+    chunk_generation,
+    biome_classification,
+    chunk_streaming
+);
+criterion_main!(benches);
@@ -0,0 +1,169 @@
+use crate::tile_data::TileType;
+use bevy::prelude::*;
+use bevy_ecs_tilemap::prelude::*;
+use serde::Deserialize;
+use std::collections::HashMap;
+
+// Blob autotiling, see http://www.cr31.co.uk/stagecast/wang/blob.html
+// Tiles blend into the tile type below them, so any neighbour of the same or a higher tile type
+// counts as connected. Corner bits only matter if both adjacent edges are connected, which leaves
+// 47 distinct tiles per tile type.
+
+const NORTH: u8 = 1 << 0;
+const NORTH_EAST: u8 = 1 << 1;
+const EAST: u8 = 1 << 2;
+const SOUTH_EAST: u8 = 1 << 3;
+const SOUTH: u8 = 1 << 4;
+const SOUTH_WEST: u8 = 1 << 5;
+const WEST: u8 = 1 << 6;
+const NORTH_WEST: u8 = 1 << 7;
+
+const NEIGHBOURS: [(IVec2, u8); 8] = [
+    (IVec2::new(0, 1), NORTH),
+    (IVec2::new(1, 1), NORTH_EAST),
+    (IVec2::new(1, 0), EAST),
+    (IVec2::new(1, -1), SOUTH_EAST),
+    (IVec2::new(0, -1), SOUTH),
+    (IVec2::new(-1, -1), SOUTH_WEST),
+    (IVec2::new(-1, 0), WEST),
+    (IVec2::new(-1, 1), NORTH_WEST),
+];
+
+const FULLY_CONNECTED: u8 = u8::MAX;
+const BLOB_INDICES: [u8; 256] = build_blob_indices();
+
+const fn reduce_mask(mask: u8) -> u8 {
+    let mut reduced = mask & (NORTH | EAST | SOUTH | WEST);
+    if mask & NORTH_EAST != 0 && mask & NORTH != 0 && mask & EAST != 0 {
+        reduced |= NORTH_EAST;
+    }
+    if mask & SOUTH_EAST != 0 && mask & SOUTH != 0 && mask & EAST != 0 {
+        reduced |= SOUTH_EAST;
+    }
+    if mask & SOUTH_WEST != 0 && mask & SOUTH != 0 && mask & WEST != 0 {
+        reduced |= SOUTH_WEST;
+    }
+    if mask & NORTH_WEST != 0 && mask & NORTH != 0 && mask & WEST != 0 {
+        reduced |= NORTH_WEST;
+    }
+    reduced
+}
+
+const fn build_blob_indices() -> [u8; 256] {
+    let mut canonical = [0u8; 256];
+    let mut next = 0;
+    let mut mask = 0;
+    while mask < 256 {
+        if reduce_mask(mask as u8) == mask as u8 {
+            canonical[mask] = next;
+            next += 1;
+        }
+        mask += 1;
+    }
+
+    let mut indices = [0u8; 256];
+    let mut mask = 0;
+    while mask < 256 {
+        indices[mask] = canonical[reduce_mask(mask as u8) as usize];
+        mask += 1;
+    }
+    indices
+}
+
+pub fn neighbour_mask(
+    tile_type: TileType,
+    world_tile_pos: IVec2,
+    get_tile_type: impl Fn(IVec2) -> TileType,
+) -> u8 {
+    NEIGHBOURS
+        .iter()
+        .filter(|(offset, _)| get_tile_type(world_tile_pos + *offset) >= tile_type)
+        .fold(0, |mask, (_, bit)| mask | bit)
+}
+
+pub fn tile_hash(world_tile_pos: IVec2, seed: u32) -> u32 {
+    let mut hash = seed
+        ^ (world_tile_pos.x as u32).wrapping_mul(0x27d4_eb2d)
+        ^ (world_tile_pos.y as u32).wrapping_mul(0x1656_67b1);
+    hash = (hash ^ (hash >> 15)).wrapping_mul(0x2c1b_3c6d);
+    hash = (hash ^ (hash >> 12)).wrapping_mul(0x297a_2d39);
+    hash ^ (hash >> 15)
+}
+
+const TILESET_CONFIG: &str = include_str!("../assets/tilesets/terrain.ron");
+
+#[derive(Resource, Clone, Deserialize)]
+pub struct Tileset {
+    pub texture: String,
+    pub columns: u32,
+    terrain: HashMap<TileType, TerrainTiles>,
+    // Plain white tile, colored in by the data layer view modes.
+    overlay: AtlasTile,
+}
+
+#[derive(Clone, Deserialize)]
+struct AtlasTile {
+    row: u32,
+    column: u32,
+}
+
+#[derive(Clone, Deserialize)]
+struct TerrainTiles {
+    row: u32,
+    #[serde(default)]
+    variants: Vec<TileVariant>,
+}
+
+#[derive(Clone, Deserialize)]
+struct TileVariant {
+    column: u32,
+    weight: u32,
+}
+
+impl Default for Tileset {
+    fn default() -> Self {
+        ron::from_str(TILESET_CONFIG).expect("tileset config should be valid")
+    }
+}
+
+impl Tileset {
+    pub fn texture_index(&self, tile_type: TileType, neighbour_mask: u8) -> TileTextureIndex {
+        let row = self.terrain[&tile_type].row;
+        TileTextureIndex(row * self.columns + BLOB_INDICES[neighbour_mask as usize] as u32)
+    }
+
+    pub fn tile_type_of(&self, texture_index: TileTextureIndex) -> Option<TileType> {
+        let row = texture_index.0 / self.columns;
+        self.terrain
+            .iter()
+            .find(|(_, terrain)| terrain.row == row)
+            .map(|(tile_type, _)| *tile_type)
+    }
+
+    pub fn overlay_texture_index(&self) -> TileTextureIndex {
+        TileTextureIndex(self.overlay.row * self.columns + self.overlay.column)
+    }
+
+    pub fn variant_texture_index(
+        &self,
+        tile_type: TileType,
+        neighbour_mask: u8,
+        tile_hash: u32,
+    ) -> TileTextureIndex {
+        let terrain = &self.terrain[&tile_type];
+        let total_weight: u32 = terrain.variants.iter().map(|variant| variant.weight).sum();
+        if neighbour_mask != FULLY_CONNECTED || total_weight == 0 {
+            return self.texture_index(tile_type, neighbour_mask);
+        }
+
+        let mut roll = tile_hash % total_weight;
+        for variant in &terrain.variants {
+            if roll < variant.weight {
+                return TileTextureIndex(terrain.row * self.columns + variant.column);
+            }
+            roll -= variant.weight;
+        }
+
+        self.texture_index(tile_type, neighbour_mask)
+    }
+}
@@ -0,0 +1,197 @@
+use noise::permutationtable::{NoiseHasher, PermutationTable};
+
+// Points evaluated together. Every step below is a plain loop over the lanes without branches,
+// which the compiler turns into SIMD instructions on targets that have them, wasm simd128
+// included.
+pub const LANES: usize = 4;
+pub type Lanes = [f64; LANES];
+
+const STRETCH_CONSTANT: f64 = -0.211_324_865_405_187;
+const SQUISH_CONSTANT: f64 = 0.366_025_403_784_439;
+const NORM_CONSTANT: f64 = 1.0 / 14.0;
+const DIAG: f64 = std::f64::consts::FRAC_1_SQRT_2;
+const GRADIENTS_X: [f64; 8] = [1.0, -1.0, 0.0, 0.0, DIAG, -DIAG, DIAG, -DIAG];
+const GRADIENTS_Y: [f64; 8] = [0.0, 0.0, 1.0, -1.0, DIAG, DIAG, -DIAG, -DIAG];
+
+// Same as BasicMulti::DEFAULT_*, except for the frequency the generator sets.
+const OCTAVES: usize = 6;
+const LACUNARITY: f64 = std::f64::consts::PI * 2.0 / 3.0;
+const PERSISTENCE: f64 = 0.5;
+
+fn lanes(f: impl Fn(usize) -> f64) -> Lanes {
+    std::array::from_fn(f)
+}
+
+// f64::floor is a library call on targets without a rounding instruction, which would keep the
+// loops from being vectorized. Exact for everything that fits in an i64.
+fn floor(value: f64) -> f64 {
+    let truncated = value as i64 as f64;
+    if truncated > value {
+        truncated - 1.0
+    } else {
+        truncated
+    }
+}
+
+// Port of noise::OpenSimplex in 2D that evaluates LANES points at once. Does the same floating
+// point operations in the same order, so it returns exactly the same values, which keeps existing
+// seeds and world codes producing the same worlds.
+#[derive(Clone)]
+pub struct BatchOpenSimplex {
+    permutation: [u8; 256],
+}
+
+impl BatchOpenSimplex {
+    pub fn new(seed: u32) -> Self {
+        // Hashing a single value returns the table entry for it.
+        let table = PermutationTable::new(seed);
+        BatchOpenSimplex {
+            permutation: std::array::from_fn(|i| table.hash(&[i as isize]) as u8),
+        }
+    }
+
+    fn hash(&self, x: f64, y: f64) -> usize {
+        let x = (x as i64 & 0xff) as usize;
+        let y = (y as i64 & 0xff) as usize;
+        self.permutation[self.permutation[x] as usize ^ y] as usize
+    }
+
+    // Contribution of the lattice vertex at the cell origin plus the given offset.
+    fn contribute(
+        &self,
+        floor: (Lanes, Lanes),
+        relative: (Lanes, Lanes),
+        offset: (Lanes, Lanes),
+    ) -> Lanes {
+        let gradient: [usize; LANES] = std::array::from_fn(|i| {
+            self.hash(floor.0[i] + offset.0[i], floor.1[i] + offset.1[i]) % 8
+        });
+        let dx =
+            lanes(|i| relative.0[i] - SQUISH_CONSTANT * (offset.0[i] + offset.1[i]) - offset.0[i]);
+        let dy =
+            lanes(|i| relative.1[i] - SQUISH_CONSTANT * (offset.0[i] + offset.1[i]) - offset.1[i]);
+        lanes(|i| {
+            let t = 2.0 - (dx[i] * dx[i] + dy[i] * dy[i]);
+            let (gradient_x, gradient_y) = (GRADIENTS_X[gradient[i]], GRADIENTS_Y[gradient[i]]);
+            // Same as t.powi(4), which is lowered to two squarings.
+            let t2 = t * t;
+            let value = t2 * t2 * (dx[i] * gradient_x + dy[i] * gradient_y);
+            if t > 0.0 {
+                value
+            } else {
+                0.0
+            }
+        })
+    }
+
+    pub fn get(&self, x: Lanes, y: Lanes) -> Lanes {
+        let stretch_offset = lanes(|i| (x[i] + y[i]) * STRETCH_CONSTANT);
+        let stretched_x = lanes(|i| x[i] + stretch_offset[i]);
+        let stretched_y = lanes(|i| y[i] + stretch_offset[i]);
+        let floor_x = lanes(|i| floor(stretched_x[i]));
+        let floor_y = lanes(|i| floor(stretched_y[i]));
+        let squish_offset = lanes(|i| (floor_x[i] + floor_y[i]) * SQUISH_CONSTANT);
+        let region_sum = lanes(|i| (stretched_x[i] - floor_x[i]) + (stretched_y[i] - floor_y[i]));
+        let relative = (
+            lanes(|i| x[i] - (floor_x[i] + squish_offset[i])),
+            lanes(|i| y[i] - (floor_y[i] + squish_offset[i])),
+        );
+        let floor = (floor_x, floor_y);
+
+        let right = self.contribute(floor, relative, ([1.0; LANES], [0.0; LANES]));
+        let down = self.contribute(floor, relative, ([0.0; LANES], [1.0; LANES]));
+        // The far corner in the lower right triangle of the cell, the origin in the upper left.
+        let corner = lanes(|i| if region_sum[i] > 1.0 { 1.0 } else { 0.0 });
+        let diagonal = self.contribute(floor, relative, (corner, corner));
+        lanes(|i| (0.0 + right[i] + down[i] + diagonal[i]) * NORM_CONSTANT)
+    }
+}
+
+// Port of noise::BasicMulti<OpenSimplex> in 2D with the default octaves, lacunarity and
+// persistence, returning the same values as BasicMulti::new(seed).set_frequency(frequency).
+#[derive(Clone)]
+pub struct BatchBasicMulti {
+    octaves: Vec<BatchOpenSimplex>,
+    frequency: f64,
+}
+
+impl BatchBasicMulti {
+    pub fn new(seed: u32, frequency: f64) -> Self {
+        BatchBasicMulti {
+            // BasicMulti adds without wrapping, which panics in debug builds for seeds close to
+            // u32::MAX. Everywhere else the seeds are the same.
+            octaves: (0..OCTAVES)
+                .map(|octave| BatchOpenSimplex::new(seed.wrapping_add(octave as u32)))
+                .collect(),
+            frequency,
+        }
+    }
+
+    pub fn get(&self, x: Lanes, y: Lanes) -> Lanes {
+        let mut x = lanes(|i| x[i] * self.frequency);
+        let mut y = lanes(|i| y[i] * self.frequency);
+        let mut result = self.octaves[0].get(x, y);
+        for (octave, source) in self.octaves.iter().enumerate().skip(1) {
+            x = lanes(|i| x[i] * LACUNARITY);
+            y = lanes(|i| y[i] * LACUNARITY);
+            let signal = source.get(x, y);
+            let amplitude = PERSISTENCE.powi(octave as i32);
+            result = lanes(|i| result[i] + signal[i] * amplitude * result[i]);
+        }
+        lanes(|i| result[i] * 0.5)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use noise::{MultiFractal, NoiseFn};
+
+    // Points around the origin and far from it, at the resolutions the generator allows.
+    fn points() -> Vec<[f64; 2]> {
+        let mut points = Vec::new();
+        for resolution in [0.001, 0.022, 0.035, 0.1] {
+            for y in -20..20 {
+                for x in -20..20 {
+                    for base in [0, 100_000] {
+                        points.push([
+                            (base + x * 7) as f64 * resolution,
+                            (y * 13 - base) as f64 * resolution,
+                        ]);
+                    }
+                }
+            }
+        }
+        points
+    }
+
+    fn check(expected: impl Fn([f64; 2]) -> f64, actual: impl Fn(Lanes, Lanes) -> Lanes) {
+        for chunk in points().chunks_exact(LANES) {
+            let values = actual(
+                std::array::from_fn(|i| chunk[i][0]),
+                std::array::from_fn(|i| chunk[i][1]),
+            );
+            for (point, value) in chunk.iter().zip(values) {
+                assert_eq!(expected(*point), value, "at {point:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn open_simplex_matches_the_noise_crate() {
+        for seed in [0, 42, 1_234_567, u32::MAX] {
+            let expected = noise::OpenSimplex::new(seed);
+            let actual = BatchOpenSimplex::new(seed);
+            check(|point| expected.get(point), |x, y| actual.get(x, y));
+        }
+    }
+
+    #[test]
+    fn basic_multi_matches_the_noise_crate() {
+        for seed in [0, 42, 1_234_567] {
+            let expected = noise::BasicMulti::<noise::OpenSimplex>::new(seed).set_frequency(5.0);
+            let actual = BatchBasicMulti::new(seed, 5.0);
+            check(|point| expected.get(point), |x, y| actual.get(x, y));
+        }
+    }
+}
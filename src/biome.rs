@@ -61,3 +61,61 @@ impl Biome {
         TileType::Stone
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn thresholds_belong_to_the_next_tile_type() {
+        assert_eq!(REGULAR.evaluate(-0.41), TileType::DeepWater);
+        assert_eq!(REGULAR.evaluate(-0.4), TileType::Water);
+        assert_eq!(REGULAR.evaluate(-0.3), TileType::Sand);
+        assert_eq!(REGULAR.evaluate(-0.2), TileType::Grass);
+        assert_eq!(REGULAR.evaluate(0.4), TileType::Stone);
+        assert_eq!(REGULAR.evaluate(1.0), TileType::Stone);
+    }
+
+    // Sand and grass share a threshold, so these biomes go straight from sand to stone.
+    #[test]
+    fn biomes_without_grass() {
+        assert_eq!(WET.evaluate(0.29), TileType::Sand);
+        assert_eq!(WET.evaluate(0.3), TileType::Stone);
+        assert_eq!(DRY.evaluate(0.19), TileType::Sand);
+        assert_eq!(DRY.evaluate(0.2), TileType::Stone);
+    }
+
+    #[test]
+    fn full_strength_matches_a_single_biome() {
+        for height in [-1.0, -0.6, -0.45, -0.4, -0.3, -0.2, 0.0, 0.2, 0.3, 0.4, 1.0] {
+            assert_eq!(
+                REGULAR.evaluate_multibiome(&WET, height, 1.0),
+                REGULAR.evaluate(height)
+            );
+            assert_eq!(
+                REGULAR.evaluate_multibiome(&DRY, height, 0.0),
+                DRY.evaluate(height)
+            );
+        }
+    }
+
+    #[test]
+    fn thresholds_blend_between_biomes() {
+        // Deep water ends halfway between -0.4 and -0.1.
+        assert_eq!(
+            REGULAR.evaluate_multibiome(&WET, -0.26, 0.5),
+            TileType::DeepWater
+        );
+        assert_eq!(
+            REGULAR.evaluate_multibiome(&WET, -0.24, 0.5),
+            TileType::Water
+        );
+        // Neither biome alone would turn this into sand.
+        assert_eq!(REGULAR.evaluate(-0.05), TileType::Grass);
+        assert_eq!(WET.evaluate(-0.05), TileType::Water);
+        assert_eq!(
+            REGULAR.evaluate_multibiome(&WET, -0.05, 0.5),
+            TileType::Sand
+        );
+    }
+}
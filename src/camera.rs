@@ -40,7 +40,7 @@ pub enum CameraAction {
 
 fn init(mut commands: Commands) {
     let mut camera = Camera2dBundle::default();
-    camera.projection.scaling_mode = ScalingMode::WindowSize(MIN_ZOOM);
+    camera.projection.scaling_mode = ScalingMode::WindowSize(DEFAULT_ZOOM);
 
     commands
         .spawn(camera)
@@ -103,27 +103,38 @@ fn move_camera(
     cursor_pos.world += delta.truncate();
 }
 
+// Screen pixels per world unit. Far below 0.25 tiles are only a few pixels wide, at which point
+// the map switches to chunk textures.
 const MAX_ZOOM: f32 = 2.0;
-const MIN_ZOOM: f32 = 0.25;
-
-fn zoom_camera(
-    mut query: Query<(&mut OrthographicProjection, &ActionState<CameraAction>), With<Camera2d>>,
-) {
-    let (mut projection, action_state) = query.single_mut();
-
-    let current_scaling = match projection.scaling_mode {
+const MIN_ZOOM: f32 = 1.0 / 128.0;
+const DEFAULT_ZOOM: f32 = 0.25;
+// Zooming is multiplicative, so every step feels the same no matter how far out the camera is.
+const ZOOM_STEP: f32 = 1.25;
+
+// Screen pixels per world unit.
+pub fn zoom(projection: &OrthographicProjection) -> f32 {
+    match projection.scaling_mode {
         ScalingMode::Fixed { .. } => 1.0,
         ScalingMode::WindowSize(x) => x,
         ScalingMode::AutoMin { .. } => 1.0,
         ScalingMode::AutoMax { .. } => 1.0,
         ScalingMode::FixedVertical(_) => 1.0,
         ScalingMode::FixedHorizontal(_) => 1.0,
-    };
+    }
+}
+
+fn zoom_camera(
+    mut query: Query<(&mut OrthographicProjection, &ActionState<CameraAction>), With<Camera2d>>,
+) {
+    let (mut projection, action_state) = query.single_mut();
 
+    let current_scaling = zoom(&projection);
     if action_state.pressed(CameraAction::ZoomIn) && current_scaling < MAX_ZOOM {
-        projection.scaling_mode = ScalingMode::WindowSize(current_scaling + 0.25)
+        projection.scaling_mode =
+            ScalingMode::WindowSize((current_scaling * ZOOM_STEP).min(MAX_ZOOM))
     } else if action_state.pressed(CameraAction::ZoomOut) && current_scaling > MIN_ZOOM {
-        projection.scaling_mode = ScalingMode::WindowSize(current_scaling - 0.25)
+        projection.scaling_mode =
+            ScalingMode::WindowSize((current_scaling / ZOOM_STEP).max(MIN_ZOOM))
     }
 }
 
@@ -0,0 +1,95 @@
+use crate::game_map::ChunkSamples;
+use bevy::prelude::*;
+use std::collections::{BTreeMap, HashMap};
+
+// Samples of recently despawned chunks, so scrolling back to an area doesn't sample the noise
+// again. Chunks that were despawned longest ago are dropped first once the cache is full.
+#[derive(Default)]
+pub struct ChunkCache {
+    entries: HashMap<IVec2, (u64, ChunkSamples)>,
+    // Cached chunks by the stamp they were inserted with, oldest first.
+    order: BTreeMap<u64, IVec2>,
+    // Incremented on every insert.
+    clock: u64,
+}
+
+impl ChunkCache {
+    // Removes the samples from the cache, the chunk holds on to them while it's spawned.
+    pub fn take(&mut self, chunk_pos: IVec2) -> Option<ChunkSamples> {
+        let (stamp, samples) = self.entries.remove(&chunk_pos)?;
+        self.order.remove(&stamp);
+        Some(samples)
+    }
+
+    pub fn insert(&mut self, chunk_pos: IVec2, samples: ChunkSamples, capacity: usize) {
+        self.clock += 1;
+        if let Some((stamp, _)) = self.entries.insert(chunk_pos, (self.clock, samples)) {
+            self.order.remove(&stamp);
+        }
+        self.order.insert(self.clock, chunk_pos);
+        while self.entries.len() > capacity {
+            let Some((_, oldest)) = self.order.pop_first() else {
+                break;
+            };
+            self.entries.remove(&oldest);
+        }
+    }
+
+    pub fn retain(&mut self, mut keep: impl FnMut(IVec2) -> bool) {
+        let order = &mut self.order;
+        self.entries.retain(|chunk_pos, (stamp, _)| {
+            let kept = keep(*chunk_pos);
+            if !kept {
+                order.remove(stamp);
+            }
+            kept
+        });
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.order.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tile_data::TerrainGrid;
+
+    fn samples() -> ChunkSamples {
+        ChunkSamples {
+            tiles: TerrainGrid::new(IVec2::ZERO, UVec2::ONE, vec![0.0], vec![0.0]),
+            river_flow: None,
+        }
+    }
+
+    #[test]
+    fn drops_the_oldest_chunks_first() {
+        let mut cache = ChunkCache::default();
+        for x in 0..4 {
+            cache.insert(IVec2::new(x, 0), samples(), 3);
+        }
+        // Inserting a chunk again makes it the newest one.
+        cache.insert(IVec2::new(1, 0), samples(), 3);
+        cache.insert(IVec2::new(4, 0), samples(), 3);
+        assert!(cache.take(IVec2::new(0, 0)).is_none());
+        assert!(cache.take(IVec2::new(2, 0)).is_none());
+        assert!(cache.take(IVec2::new(1, 0)).is_some());
+        assert!(cache.take(IVec2::new(1, 0)).is_none());
+        assert_eq!(cache.entries.len(), cache.order.len());
+    }
+
+    #[test]
+    fn retained_chunks_keep_their_order() {
+        let mut cache = ChunkCache::default();
+        for x in 0..4 {
+            cache.insert(IVec2::new(x, 0), samples(), 4);
+        }
+        cache.retain(|chunk_pos| chunk_pos.x != 0);
+        cache.insert(IVec2::new(4, 0), samples(), 3);
+        assert!(cache.take(IVec2::new(1, 0)).is_none());
+        assert!(cache.take(IVec2::new(2, 0)).is_some());
+        assert_eq!(cache.entries.len(), cache.order.len());
+    }
+}
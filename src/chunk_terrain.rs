@@ -0,0 +1,42 @@
+use crate::coords;
+use crate::game_map::{ChunkData, CHUNK_SIZE};
+use crate::tile_data::{TerrainGrid, TileData};
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+
+// Terrain of a spawned chunk, stored on its tilemap as one grid per layer instead of a component
+// on every tile entity.
+#[derive(Component)]
+pub struct ChunkTerrain(TerrainGrid);
+
+impl ChunkTerrain {
+    // Copies the chunk's rows out of samples that may be padded around it.
+    pub fn new(chunk_pos: IVec2, tiles: &TerrainGrid) -> Self {
+        ChunkTerrain(tiles.crop(coords::chunk_origin(chunk_pos), CHUNK_SIZE))
+    }
+
+    pub fn tile_data(&self, local_pos: UVec2) -> TileData {
+        self.0.get(self.0.origin() + local_pos.as_ivec2())
+    }
+}
+
+// Looks up tiles of spawned chunks by their world tile position.
+#[derive(SystemParam)]
+pub struct Terrain<'w, 's> {
+    chunks: Query<'w, 's, (&'static ChunkData, &'static ChunkTerrain)>,
+}
+
+impl Terrain<'_, '_> {
+    pub fn chunk(&self, chunk_pos: IVec2) -> Option<&ChunkTerrain> {
+        self.chunks
+            .iter()
+            .find(|(chunk_data, _)| chunk_data.position == chunk_pos)
+            .map(|(_, terrain)| terrain)
+    }
+
+    // None if the chunk holding the tile isn't spawned.
+    pub fn tile_data(&self, world_tile_pos: IVec2) -> Option<TileData> {
+        let terrain = self.chunk(coords::tile_to_chunk_pos(world_tile_pos))?;
+        Some(terrain.tile_data(coords::tile_to_local_pos(world_tile_pos)))
+    }
+}
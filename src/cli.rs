@@ -0,0 +1,263 @@
+use crate::autotile::Tileset;
+use crate::data_export::{self, DataFormat};
+use crate::export::{self, ExportLayer, TileRegion};
+use crate::game_map::{TilePainter, TileRenderSettings};
+use crate::noise_generator::NoiseGenerator;
+use crate::preset::GeneratorPreset;
+use crate::tile_data::TileType;
+use crate::tile_deltas::TileDeltas;
+use crate::tiled::{self, TiledFormat};
+use crate::world_code;
+use bevy::prelude::*;
+use clap::{Args, Parser, Subcommand};
+use std::error::Error;
+use std::path::PathBuf;
+
+/// Procedural 2D map generation. Opens the game window unless a subcommand is given.
+#[derive(Parser)]
+#[command(version)]
+pub struct Cli {
+    /// Start the game with a built-in or saved preset, or a preset file
+    #[arg(long)]
+    preset: Option<String>,
+    /// Start the game with a world code copied from the settings window
+    #[arg(long, conflicts_with = "preset")]
+    world: Option<String>,
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+impl Cli {
+    pub fn startup_preset(&self) -> Result<Option<GeneratorPreset>, Box<dyn Error>> {
+        if let Some(code) = &self.world {
+            return world_code::decode(code).map(Some);
+        }
+        if let Some(name_or_path) = &self.preset {
+            return GeneratorPreset::find(name_or_path).map(Some);
+        }
+        world_code::from_url()
+    }
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Render a region of the world to a PNG file, or a Tiled map if it ends in .tmx or .tmj
+    #[command(alias = "export")]
+    Render(RenderArgs),
+    /// Write the raw height or humidity values of a region, plus a .meta.ron file with metadata
+    #[command(alias = "export-data")]
+    Generate(GenerateArgs),
+    /// Print tile type distribution and noise ranges of a region for one or more seeds
+    Stats(StatsArgs),
+}
+
+#[derive(Args)]
+struct WorldArgs {
+    /// Name of a built-in or saved preset, or a path to a preset file
+    #[arg(long)]
+    preset: Option<String>,
+    /// World code copied from the settings window, replaces preset, seed and resolution
+    #[arg(long, conflicts_with_all = ["preset", "seed", "resolution"])]
+    world: Option<String>,
+    /// Defaults to the seed of the preset, or 42
+    #[arg(long)]
+    seed: Option<String>,
+    /// Defaults to the resolution of the preset
+    #[arg(long)]
+    resolution: Option<f64>,
+}
+
+impl WorldArgs {
+    fn noise_generator(&self) -> Result<NoiseGenerator, Box<dyn Error>> {
+        if let Some(code) = &self.world {
+            return Ok(world_code::decode(code)?.noise_generator());
+        }
+        let mut preset = load_preset(self.preset.as_deref(), self.resolution)?;
+        if let Some(seed) = &self.seed {
+            preset.seed = seed.clone();
+        }
+        Ok(preset.noise_generator())
+    }
+}
+
+fn load_preset(
+    name_or_path: Option<&str>,
+    resolution: Option<f64>,
+) -> Result<GeneratorPreset, Box<dyn Error>> {
+    let mut preset = match name_or_path {
+        Some(name_or_path) => GeneratorPreset::find(name_or_path)?,
+        None => GeneratorPreset::default(),
+    };
+    if let Some(resolution) = resolution {
+        preset.noise_values.resolution = resolution;
+    }
+    Ok(preset)
+}
+
+#[derive(Args)]
+struct RegionArgs {
+    /// Tiles to include, as X,Y,WIDTH,HEIGHT with X,Y being the bottom left tile
+    #[arg(
+        long,
+        value_parser = parse_region,
+        default_value = "0,0,256,256",
+        allow_hyphen_values = true
+    )]
+    region: TileRegion,
+}
+
+// Parses "X,Y,WIDTH,HEIGHT", X and Y being the bottom left tile.
+fn parse_region(value: &str) -> Result<TileRegion, String> {
+    let parts: Vec<&str> = value.split(',').map(str::trim).collect();
+    let [x, y, width, height] = parts[..] else {
+        return Err(String::from("expected X,Y,WIDTH,HEIGHT"));
+    };
+    let invalid = |error: std::num::ParseIntError| error.to_string();
+    Ok(TileRegion {
+        origin: IVec2::new(x.parse().map_err(invalid)?, y.parse().map_err(invalid)?),
+        size: UVec2::new(
+            width.parse().map_err(invalid)?,
+            height.parse().map_err(invalid)?,
+        ),
+    })
+}
+
+#[derive(Args)]
+pub struct RenderArgs {
+    #[command(flatten)]
+    world: WorldArgs,
+    #[command(flatten)]
+    area: RegionArgs,
+    #[arg(long, value_enum, default_value_t = ExportLayer::TileTypes)]
+    layer: ExportLayer,
+    /// Draw the tile sprites instead of one pixel per tile
+    #[arg(long)]
+    sprites: bool,
+    #[arg(long, short)]
+    out: PathBuf,
+}
+
+#[derive(Args)]
+pub struct GenerateArgs {
+    #[command(flatten)]
+    world: WorldArgs,
+    #[command(flatten)]
+    area: RegionArgs,
+    #[arg(long, value_enum, default_value_t = ExportLayer::Height)]
+    layer: ExportLayer,
+    #[arg(long, value_enum, default_value_t = DataFormat::Png16)]
+    format: DataFormat,
+    #[arg(long, short)]
+    out: PathBuf,
+}
+
+#[derive(Args)]
+pub struct StatsArgs {
+    /// Name of a built-in or saved preset, or a path to a preset file
+    #[arg(long)]
+    preset: Option<String>,
+    /// Takes several seeds to compare them, defaults to the seed of the preset
+    #[arg(long = "seed", num_args = 1..)]
+    seeds: Vec<String>,
+    /// Defaults to the resolution of the preset
+    #[arg(long)]
+    resolution: Option<f64>,
+    #[command(flatten)]
+    area: RegionArgs,
+}
+
+pub fn run(command: Command) -> Result<(), Box<dyn Error>> {
+    match command {
+        Command::Render(args) => run_render(args),
+        Command::Generate(args) => run_generate(args),
+        Command::Stats(args) => run_stats(args),
+    }
+}
+
+fn run_render(args: RenderArgs) -> Result<(), Box<dyn Error>> {
+    let noise = args.world.noise_generator()?;
+    let tileset = Tileset::default();
+    let render_settings = TileRenderSettings::default();
+    let deltas = TileDeltas::default();
+    let painter = TilePainter {
+        noise: &noise,
+        tileset: &tileset,
+        render_settings: &render_settings,
+        deltas: &deltas,
+        view_mode: args.layer.view_mode(),
+    };
+    let region = args.area.region;
+    export::check_image_size(region, args.sprites)?;
+    if TiledFormat::from_path(&args.out).is_some() {
+        tiled::export_tiled(&painter, region, &args.out)?;
+        println!("Exported Tiled map to {}", args.out.display());
+        return Ok(());
+    }
+
+    let tileset_image = if args.sprites {
+        Some(export::load_tileset_image(&tileset)?)
+    } else {
+        None
+    };
+    export::render_region(&painter, region, tileset_image.as_ref()).save(&args.out)?;
+    println!("Exported {} to {}", args.layer.name(), args.out.display());
+    Ok(())
+}
+
+fn run_generate(args: GenerateArgs) -> Result<(), Box<dyn Error>> {
+    export::check_image_size(args.area.region, false)?;
+    let noise = args.world.noise_generator()?;
+    let sidecar_path =
+        data_export::export_data(&noise, args.area.region, args.layer, args.format, &args.out)?;
+    println!(
+        "Exported {} as {} to {}, metadata in {}",
+        args.layer.name(),
+        args.format.name(),
+        args.out.display(),
+        sidecar_path.display()
+    );
+    Ok(())
+}
+
+fn run_stats(args: StatsArgs) -> Result<(), Box<dyn Error>> {
+    let preset = load_preset(args.preset.as_deref(), args.resolution)?;
+    let seeds = if args.seeds.is_empty() {
+        vec![preset.seed.clone()]
+    } else {
+        args.seeds
+    };
+
+    print!("{:<12}", "seed");
+    for tile_type in TileType::ALL {
+        print!("{:>10}", format!("{tile_type:?}"));
+    }
+    println!("{:>22}{:>22}", "height", "humidity");
+    for seed in &seeds {
+        let noise = NoiseGenerator::new(seed, preset.noise_values);
+        let tiles = noise.get_tile_data_grid(args.area.region.origin, args.area.region.size);
+        let tile_count = tiles.height().values().len() as f32;
+
+        print!("{seed:<12}");
+        for tile_type in TileType::ALL {
+            let count = tiles
+                .tiles()
+                .filter(|tile_data| tile_data.get_tile_type() == tile_type)
+                .count();
+            print!("{:>9.1}%", count as f32 / tile_count * 100.0);
+        }
+        println!(
+            "{:>22}{:>22}",
+            value_range(tiles.height().values()),
+            value_range(tiles.humidity().values())
+        );
+    }
+    Ok(())
+}
+
+// Formats as "min..max ~mean".
+fn value_range(values: &[f32]) -> String {
+    let min = values.iter().copied().fold(f32::INFINITY, f32::min);
+    let max = values.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+    let mean = values.iter().sum::<f32>() / values.len() as f32;
+    format!("{min:.2}..{max:.2} ~{mean:.2}")
+}
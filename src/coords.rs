@@ -0,0 +1,190 @@
+use crate::game_map::{CHUNK_SIZE, TILE_SIZE};
+use bevy::prelude::*;
+
+// Conversions between the three coordinate spaces of the map:
+//   world: pixels, as used by transforms and the camera
+//   tile: one unit per tile, tile (0, 0) is centered on the world origin
+//   chunk: one unit per CHUNK_SIZE tiles, chunk (0, 0) holds tiles (0, 0) to CHUNK_SIZE - 1
+// Everything rounds towards negative infinity, so every cell has the same size on both sides of
+// the origin.
+
+pub fn tile_size() -> Vec2 {
+    Vec2::new(TILE_SIZE.x, TILE_SIZE.y)
+}
+
+// Tiles are centered on their position, so a tile covers half a tile in every direction.
+pub fn world_to_tile_pos(world_pos: Vec2) -> IVec2 {
+    (world_pos / tile_size() + 0.5).floor().as_ivec2()
+}
+
+// Center of the tile.
+pub fn tile_to_world_pos(world_tile_pos: IVec2) -> Vec2 {
+    world_tile_pos.as_vec2() * tile_size()
+}
+
+// Continuous version of world_to_tile_pos, tile (0, 0) covering 0.0..1.0.
+pub fn world_to_tile_space(world_pos: Vec2) -> Vec2 {
+    world_pos / tile_size() + 0.5
+}
+
+pub fn tile_space_to_world(tile_space_pos: Vec2) -> Vec2 {
+    (tile_space_pos - 0.5) * tile_size()
+}
+
+pub fn tile_to_chunk_pos(world_tile_pos: IVec2) -> IVec2 {
+    world_tile_pos.div_euclid(CHUNK_SIZE.as_ivec2())
+}
+
+// Position of the tile within its chunk.
+pub fn tile_to_local_pos(world_tile_pos: IVec2) -> UVec2 {
+    world_tile_pos.rem_euclid(CHUNK_SIZE.as_ivec2()).as_uvec2()
+}
+
+// First tile of the chunk.
+pub fn chunk_origin(chunk_pos: IVec2) -> IVec2 {
+    chunk_pos * CHUNK_SIZE.as_ivec2()
+}
+
+// Where the chunk's tilemap is placed, which is the center of its first tile.
+pub fn chunk_to_world_pos(chunk_pos: IVec2) -> Vec2 {
+    tile_to_world_pos(chunk_origin(chunk_pos))
+}
+
+pub fn world_to_chunk_pos(world_pos: Vec2) -> IVec2 {
+    tile_to_chunk_pos(world_to_tile_pos(world_pos))
+}
+
+// Continuous version of world_to_chunk_pos, chunk (0, 0) covering 0.0..1.0.
+pub fn world_to_chunk_space(world_pos: Vec2) -> Vec2 {
+    world_to_tile_space(world_pos) / CHUNK_SIZE.as_vec2()
+}
+
+// Inclusive range of tiles overlapping a rectangle in world space.
+pub fn tiles_overlapping(world_rect: Rect) -> (IVec2, IVec2) {
+    (
+        world_to_tile_pos(world_rect.min),
+        world_to_tile_pos(world_rect.max),
+    )
+}
+
+// Inclusive range of chunks overlapping a rectangle in world space.
+pub fn chunks_overlapping(world_rect: Rect) -> (IVec2, IVec2) {
+    (
+        world_to_chunk_pos(world_rect.min),
+        world_to_chunk_pos(world_rect.max),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CHUNK: i32 = CHUNK_SIZE.x as i32;
+    const TILE: f32 = TILE_SIZE.x;
+
+    #[test]
+    fn tiles_are_centered_on_their_position() {
+        assert_eq!(world_to_tile_pos(Vec2::ZERO), IVec2::ZERO);
+        assert_eq!(world_to_tile_pos(Vec2::splat(TILE * 0.49)), IVec2::ZERO);
+        assert_eq!(world_to_tile_pos(Vec2::splat(-TILE * 0.5)), IVec2::ZERO);
+        assert_eq!(world_to_tile_pos(Vec2::splat(TILE * 0.5)), IVec2::ONE);
+        assert_eq!(world_to_tile_pos(Vec2::splat(-TILE * 0.51)), IVec2::NEG_ONE);
+    }
+
+    #[test]
+    fn tile_round_trip() {
+        for pos in [IVec2::ZERO, IVec2::new(-1, 1), IVec2::new(-1000, 37)] {
+            assert_eq!(world_to_tile_pos(tile_to_world_pos(pos)), pos);
+        }
+    }
+
+    #[test]
+    fn tile_space_floors_to_the_tile() {
+        for world_pos in [
+            Vec2::ZERO,
+            Vec2::splat(-TILE * 0.51),
+            Vec2::new(TILE * 3.7, -0.1),
+        ] {
+            let tile_space_pos = world_to_tile_space(world_pos);
+            assert_eq!(
+                tile_space_pos.floor().as_ivec2(),
+                world_to_tile_pos(world_pos)
+            );
+            assert!(tile_space_to_world(tile_space_pos).distance(world_pos) < 1e-3);
+        }
+    }
+
+    #[test]
+    fn chunks_floor_negative_tiles() {
+        assert_eq!(tile_to_chunk_pos(IVec2::ZERO), IVec2::ZERO);
+        assert_eq!(tile_to_chunk_pos(IVec2::splat(CHUNK - 1)), IVec2::ZERO);
+        assert_eq!(tile_to_chunk_pos(IVec2::splat(CHUNK)), IVec2::ONE);
+        assert_eq!(tile_to_chunk_pos(IVec2::splat(-1)), IVec2::NEG_ONE);
+        assert_eq!(tile_to_chunk_pos(IVec2::splat(-CHUNK)), IVec2::NEG_ONE);
+        assert_eq!(
+            tile_to_chunk_pos(IVec2::splat(-CHUNK - 1)),
+            IVec2::splat(-2)
+        );
+    }
+
+    #[test]
+    fn local_positions_stay_within_the_chunk() {
+        assert_eq!(
+            tile_to_local_pos(IVec2::new(-1, 0)),
+            UVec2::new(CHUNK as u32 - 1, 0)
+        );
+        for pos in [
+            IVec2::new(-1, -CHUNK),
+            IVec2::new(5, -70),
+            IVec2::new(CHUNK * 3, 1),
+        ] {
+            let local = tile_to_local_pos(pos);
+            assert!(local.cmplt(CHUNK_SIZE).all());
+            assert_eq!(chunk_origin(tile_to_chunk_pos(pos)) + local.as_ivec2(), pos);
+        }
+    }
+
+    #[test]
+    fn chunk_zero_is_the_same_size_as_its_neighbours() {
+        let chunk_width = CHUNK as f32 * TILE;
+        let edge = -TILE * 0.5;
+        assert_eq!(world_to_chunk_pos(Vec2::splat(edge)), IVec2::ZERO);
+        assert_eq!(world_to_chunk_pos(Vec2::splat(edge - 0.01)), IVec2::NEG_ONE);
+        assert_eq!(
+            world_to_chunk_pos(Vec2::splat(edge + chunk_width - 0.01)),
+            IVec2::ZERO
+        );
+        assert_eq!(
+            world_to_chunk_pos(Vec2::splat(edge + chunk_width)),
+            IVec2::ONE
+        );
+        assert_eq!(
+            world_to_chunk_pos(Vec2::splat(edge - chunk_width)),
+            IVec2::NEG_ONE
+        );
+    }
+
+    #[test]
+    fn chunk_space_matches_chunk_positions() {
+        for pos in [
+            Vec2::ZERO,
+            Vec2::new(-3000.0, 123.0),
+            Vec2::new(-1.0, -9000.0),
+        ] {
+            assert_eq!(
+                world_to_chunk_space(pos).floor().as_ivec2(),
+                world_to_chunk_pos(pos)
+            );
+        }
+    }
+
+    #[test]
+    fn overlapping_chunks_are_symmetric_around_the_origin() {
+        let half_chunk = CHUNK as f32 * TILE * 0.5;
+        let rect = Rect::from_center_half_size(Vec2::splat(-TILE * 0.5), Vec2::splat(half_chunk));
+        assert_eq!(chunks_overlapping(rect), (IVec2::NEG_ONE, IVec2::ZERO));
+
+        let (min, max) = tiles_overlapping(Rect::new(-TILE, -TILE, TILE, TILE));
+        assert_eq!((min, max), (IVec2::NEG_ONE, IVec2::ONE));
+    }
+}
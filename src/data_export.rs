@@ -0,0 +1,180 @@
+use crate::export::{ExportLayer, TileRegion};
+use crate::noise_generator::{NoiseGenerator, NoiseValues};
+use bevy::prelude::*;
+use image::{ImageBuffer, Luma};
+use serde::Serialize;
+use std::error::Error;
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+
+// Raw noise values for use outside of the game. All formats store the region row by row, starting
+// with the northernmost row, so they line up with the PNG exports.
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Serialize, clap::ValueEnum)]
+pub enum DataFormat {
+    // Grayscale, -1 is black and 1 is white.
+    Png16,
+    // Little-endian f32 without any header.
+    Raw,
+    Csv,
+    // NumPy array of f32 with shape (height, width).
+    Npy,
+}
+
+impl DataFormat {
+    pub const ALL: [DataFormat; 4] = [
+        DataFormat::Png16,
+        DataFormat::Raw,
+        DataFormat::Csv,
+        DataFormat::Npy,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            DataFormat::Png16 => "16-bit PNG",
+            DataFormat::Raw => "RAW f32",
+            DataFormat::Csv => "CSV",
+            DataFormat::Npy => "NumPy",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            DataFormat::Png16 => "png",
+            DataFormat::Raw => "raw",
+            DataFormat::Csv => "csv",
+            DataFormat::Npy => "npy",
+        }
+    }
+}
+
+// Written next to the data, so it can be regenerated or placed back into the world.
+#[derive(Serialize)]
+struct DataExportMetadata {
+    // As entered, the same text regenerates the same world.
+    seed: String,
+    noise_values: NoiseValues,
+    layer: ExportLayer,
+    format: DataFormat,
+    origin: (i32, i32),
+    size: (u32, u32),
+    min: f32,
+    max: f32,
+}
+
+const SIDECAR_EXTENSION: &str = "meta.ron";
+
+// Can't be mistaken for the data or a preset, e.g. height.npy gets height.meta.ron.
+fn sidecar_path(path: &Path) -> PathBuf {
+    path.with_extension(SIDECAR_EXTENSION)
+}
+
+fn sample_layer(
+    noise: &NoiseGenerator,
+    region: TileRegion,
+    layer: ExportLayer,
+) -> Result<Vec<f32>, Box<dyn Error>> {
+    let tiles = noise.get_tile_data_grid(region.origin, region.size);
+    let grid = match layer {
+        ExportLayer::TileTypes => return Err("tile types have no raw data".into()),
+        ExportLayer::Height => tiles.height(),
+        ExportLayer::Humidity => tiles.humidity(),
+    };
+
+    // Rows are stored bottom up, the file starts with the top one.
+    let width = region.size.x as usize;
+    Ok(grid
+        .values()
+        .chunks_exact(width)
+        .rev()
+        .flatten()
+        .copied()
+        .collect())
+}
+
+// Returns the path of the sidecar file.
+pub fn export_data(
+    noise: &NoiseGenerator,
+    region: TileRegion,
+    layer: ExportLayer,
+    format: DataFormat,
+    path: &Path,
+) -> Result<PathBuf, Box<dyn Error>> {
+    // The metadata would overwrite the data, or the other way around.
+    if path.extension().is_some_and(|extension| extension == "ron") {
+        return Err("data can't be exported to a .ron file, that's where the metadata goes".into());
+    }
+
+    let values = sample_layer(noise, region, layer)?;
+    match format {
+        DataFormat::Png16 => write_png16(&values, region.size, path)?,
+        DataFormat::Raw => std::fs::write(path, to_le_bytes(&values))?,
+        DataFormat::Csv => std::fs::write(path, to_csv(&values, region.size.x))?,
+        DataFormat::Npy => std::fs::write(path, to_npy(&values, region.size))?,
+    }
+
+    let metadata = DataExportMetadata {
+        seed: noise.seed_text().to_string(),
+        noise_values: noise.values,
+        layer,
+        format,
+        origin: (region.origin.x, region.origin.y),
+        size: (region.size.x, region.size.y),
+        min: values.iter().copied().fold(f32::INFINITY, f32::min),
+        max: values.iter().copied().fold(f32::NEG_INFINITY, f32::max),
+    };
+    let sidecar_path = sidecar_path(path);
+    let pretty = ron::ser::PrettyConfig::default();
+    std::fs::write(
+        &sidecar_path,
+        ron::ser::to_string_pretty(&metadata, pretty)?,
+    )?;
+    Ok(sidecar_path)
+}
+
+fn write_png16(values: &[f32], size: UVec2, path: &Path) -> Result<(), Box<dyn Error>> {
+    let pixels = values
+        .iter()
+        .map(|value| (((value + 1.0) * 0.5).clamp(0.0, 1.0) * u16::MAX as f32).round() as u16)
+        .collect();
+    let image: ImageBuffer<Luma<u16>, Vec<u16>> =
+        ImageBuffer::from_raw(size.x, size.y, pixels).ok_or("region size does not match")?;
+    image.save(path)?;
+    Ok(())
+}
+
+fn to_le_bytes(values: &[f32]) -> Vec<u8> {
+    values
+        .iter()
+        .flat_map(|value| value.to_le_bytes())
+        .collect()
+}
+
+fn to_csv(values: &[f32], width: u32) -> String {
+    let mut csv = String::new();
+    for row in values.chunks(width as usize) {
+        let row: Vec<String> = row.iter().map(ToString::to_string).collect();
+        let _ = writeln!(csv, "{}", row.join(","));
+    }
+    csv
+}
+
+// See https://numpy.org/doc/stable/reference/generated/numpy.lib.format.html
+fn to_npy(values: &[f32], size: UVec2) -> Vec<u8> {
+    const MAGIC: &[u8] = b"\x93NUMPY\x01\x00";
+    let mut header = format!(
+        "{{'descr': '<f4', 'fortran_order': False, 'shape': ({}, {}), }}",
+        size.y, size.x
+    );
+    // Magic, header length and header are padded to a multiple of 64 bytes, ending with a newline.
+    let unpadded_len = MAGIC.len() + 2 + header.len() + 1;
+    header.push_str(&" ".repeat((64 - unpadded_len % 64) % 64));
+    header.push('\n');
+
+    let mut npy = Vec::with_capacity(MAGIC.len() + 2 + header.len() + values.len() * 4);
+    npy.extend_from_slice(MAGIC);
+    npy.extend_from_slice(&(header.len() as u16).to_le_bytes());
+    npy.extend_from_slice(header.as_bytes());
+    npy.extend(to_le_bytes(values));
+    npy
+}
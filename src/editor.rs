@@ -0,0 +1,484 @@
+use crate::autotile;
+use crate::coords::{self, world_to_tile_pos};
+use crate::game::CursorPos;
+use crate::game_map::{RepaintMapEvent, RepaintTilesEvent, TILE_SIZE};
+use crate::noise_generator::NoiseGenerator;
+use crate::tile_data::{Decoration, TerrainGrid, TileType};
+use crate::tile_deltas::{GeneratorShelf, TileDelta, TileDeltas};
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use bevy_egui::egui::{Align2, ComboBox, Widget};
+use bevy_egui::*;
+use leafwing_input_manager::action_state::ActionState;
+use leafwing_input_manager::input_map::InputMap;
+use leafwing_input_manager::plugin::InputManagerPlugin;
+use leafwing_input_manager::prelude::UserInput;
+use leafwing_input_manager::{Actionlike, InputManagerBundle};
+use std::collections::HashMap;
+
+// Height change per second at full strength for the raise and lower tools.
+const HEIGHT_PER_SECOND: f32 = 0.5;
+// Blend per second at full strength for smooth and flatten.
+const BLEND_PER_SECOND: f32 = 4.0;
+// Keeps decoration placement from lining up with the tile variants, which hash the same positions.
+const DECORATION_HASH_SEED: u32 = 0x5eed_7ee5;
+
+pub struct EditorPlugin;
+impl Plugin for EditorPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(InputManagerPlugin::<EditorAction>::default())
+            .init_resource::<Brush>()
+            .init_resource::<EditHistory>()
+            .add_systems(Startup, init)
+            .add_systems(Update, editor_ui_system)
+            .add_systems(Update, paint_system)
+            .add_systems(Update, draw_brush)
+            // After everything that can swap the generator during Update.
+            .add_systems(PostUpdate, shelve_edits_on_generator_change);
+    }
+}
+
+#[derive(Actionlike, PartialEq, Eq, Hash, Clone, Copy, Debug, Reflect)]
+pub enum EditorAction {
+    Paint,
+    Undo,
+    Redo,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+enum Tool {
+    Inspect,
+    PaintTileType,
+    Raise,
+    Lower,
+    Smooth,
+    Flatten,
+    PlaceDecoration,
+    RemoveDecoration,
+}
+
+impl Tool {
+    const ALL: [Tool; 8] = [
+        Tool::Inspect,
+        Tool::PaintTileType,
+        Tool::Raise,
+        Tool::Lower,
+        Tool::Smooth,
+        Tool::Flatten,
+        Tool::PlaceDecoration,
+        Tool::RemoveDecoration,
+    ];
+
+    fn name(&self) -> &'static str {
+        match self {
+            Tool::Inspect => "Inspect",
+            Tool::PaintTileType => "Paint Tile Type",
+            Tool::Raise => "Raise",
+            Tool::Lower => "Lower",
+            Tool::Smooth => "Smooth",
+            Tool::Flatten => "Flatten",
+            Tool::PlaceDecoration => "Place Decoration",
+            Tool::RemoveDecoration => "Remove Decoration",
+        }
+    }
+}
+
+#[derive(Resource)]
+pub struct Brush {
+    tool: Tool,
+    // In tiles, 0 only touches the tile below the cursor.
+    radius: u32,
+    // Fraction of the radius over which the brush fades out towards its edge.
+    falloff: f32,
+    strength: f32,
+    tile_type: TileType,
+    decoration: Decoration,
+}
+impl Default for Brush {
+    fn default() -> Self {
+        Brush {
+            tool: Tool::Inspect,
+            radius: 3,
+            falloff: 0.5,
+            strength: 0.5,
+            tile_type: TileType::Grass,
+            decoration: Decoration::Tree,
+        }
+    }
+}
+
+impl Brush {
+    // Whether a painting tool is selected, as opposed to just inspecting tiles.
+    pub fn is_editing(&self) -> bool {
+        self.tool != Tool::Inspect
+    }
+
+    fn weight(&self, offset: IVec2) -> f32 {
+        let radius = self.radius as f32 + 0.5;
+        let distance = offset.as_vec2().length();
+        let solid = radius * (1.0 - self.falloff);
+        if distance >= radius {
+            0.0
+        } else if distance <= solid {
+            1.0
+        } else {
+            1.0 - (distance - solid) / (radius - solid)
+        }
+    }
+
+    // Changes the tiles around center, `tiles` being the current state with one tile of padding
+    // around the brush. Discrete changes are dithered towards the edge instead of fading out.
+    fn apply(
+        &self,
+        center: IVec2,
+        tiles: &TerrainGrid,
+        deltas: &TileDeltas,
+        stroke: &Stroke,
+        delta_seconds: f32,
+        seed: u32,
+    ) -> Vec<(IVec2, TileDelta)> {
+        let radius = self.radius as i32;
+        let mut changes = Vec::new();
+        for y in -radius..=radius {
+            for x in -radius..=radius {
+                let offset = IVec2::new(x, y);
+                let weight = self.weight(offset);
+                if weight <= 0.0 {
+                    continue;
+                }
+
+                let pos = center + offset;
+                let tile_data = tiles.get(pos);
+                let random =
+                    autotile::tile_hash(pos, seed ^ DECORATION_HASH_SEED) as f32 / u32::MAX as f32;
+                let blend = (self.strength * weight * BLEND_PER_SECOND * delta_seconds).min(1.0);
+                let original = deltas.get(pos);
+                let mut delta = original;
+                match self.tool {
+                    Tool::Inspect => return Vec::new(),
+                    Tool::PaintTileType if random < weight => {
+                        delta.tile_type = Some(self.tile_type);
+                    }
+                    Tool::Raise | Tool::Lower => {
+                        let sign = if self.tool == Tool::Raise { 1.0 } else { -1.0 };
+                        let change =
+                            sign * self.strength * weight * HEIGHT_PER_SECOND * delta_seconds;
+                        delta.height = Some((tile_data.height + change).clamp(-1.0, 1.0));
+                    }
+                    Tool::Smooth => {
+                        let mut sum = 0.0;
+                        for ny in -1..=1 {
+                            for nx in -1..=1 {
+                                sum += tiles.height().get(pos + IVec2::new(nx, ny));
+                            }
+                        }
+                        let average = sum / 9.0;
+                        delta.height = Some(lerp(tile_data.height, average, blend));
+                    }
+                    Tool::Flatten => {
+                        delta.height = Some(lerp(tile_data.height, stroke.flatten_height, blend));
+                    }
+                    Tool::PlaceDecoration if random < weight * self.strength => {
+                        delta.decoration = Some(self.decoration);
+                    }
+                    Tool::RemoveDecoration => delta.decoration = None,
+                    _ => continue,
+                }
+                if delta != original {
+                    changes.push((pos, delta));
+                }
+            }
+        }
+        changes
+    }
+}
+
+fn lerp(from: f32, to: f32, t: f32) -> f32 {
+    from + (to - from) * t
+}
+
+// Tiles touched by the stroke that is currently being painted, with their deltas from before.
+struct Stroke {
+    before: HashMap<IVec2, TileDelta>,
+    flatten_height: f32,
+}
+
+// A finished stroke, with the deltas of every tile before and after it.
+struct Edit {
+    tiles: Vec<(IVec2, TileDelta, TileDelta)>,
+}
+
+#[derive(Resource, Default)]
+struct EditHistory {
+    undo: Vec<Edit>,
+    redo: Vec<Edit>,
+    stroke: Option<Stroke>,
+    // Undo and redo of the other generators, they come back together with their deltas.
+    other_generators: GeneratorShelf<(Vec<Edit>, Vec<Edit>)>,
+}
+
+#[derive(SystemParam)]
+struct TileEdits<'w> {
+    noise: Res<'w, NoiseGenerator>,
+    deltas: ResMut<'w, TileDeltas>,
+    repaint_tiles_event: EventWriter<'w, RepaintTilesEvent>,
+}
+
+impl TileEdits<'_> {
+    fn set(&mut self, tiles: impl IntoIterator<Item = (IVec2, TileDelta)>) {
+        let mut bounds: Option<(IVec2, IVec2)> = None;
+        for (pos, delta) in tiles {
+            self.deltas.set(pos, delta);
+            bounds = Some(match bounds {
+                Some((min, max)) => (min.min(pos), max.max(pos)),
+                None => (pos, pos),
+            });
+        }
+        if let Some((min, max)) = bounds {
+            self.repaint_tiles_event
+                .send(RepaintTilesEvent { min, max });
+        }
+    }
+
+    fn undo(&mut self, history: &mut EditHistory) {
+        if let Some(edit) = history.undo.pop() {
+            self.set(edit.tiles.iter().map(|(pos, before, _)| (*pos, *before)));
+            history.redo.push(edit);
+        }
+    }
+
+    fn redo(&mut self, history: &mut EditHistory) {
+        if let Some(edit) = history.redo.pop() {
+            self.set(edit.tiles.iter().map(|(pos, _, after)| (*pos, *after)));
+            history.undo.push(edit);
+        }
+    }
+}
+
+// Edits only make sense on the terrain they were made on, so they are put aside together with
+// their undo history once the seed or noise values change, and come back when switching back to
+// them, e.g. after a live preview or by undoing the settings. Loading a saved world brings its own
+// deltas.
+fn shelve_edits_on_generator_change(
+    noise: Res<NoiseGenerator>,
+    mut deltas: ResMut<TileDeltas>,
+    mut history: ResMut<EditHistory>,
+    mut repaint_map_event: EventWriter<RepaintMapEvent>,
+) {
+    if !noise.is_changed() {
+        return;
+    }
+
+    let history = &mut *history;
+    let mut stacks = (
+        std::mem::take(&mut history.undo),
+        std::mem::take(&mut history.redo),
+    );
+    let keep = !stacks.0.is_empty() || !stacks.1.is_empty();
+    if history.other_generators.switch(&noise, &mut stacks, keep) {
+        history.stroke = None;
+    }
+    (history.undo, history.redo) = stacks;
+
+    if deltas.switch_generator(&noise) {
+        // Chunks may already have been repainted with the other deltas this frame.
+        repaint_map_event.send(RepaintMapEvent);
+    }
+}
+
+fn init(mut commands: Commands) {
+    commands.spawn(InputManagerBundle::<EditorAction> {
+        input_map: default_input_map_editor(),
+        ..default()
+    });
+}
+
+fn editor_ui_system(
+    mut contexts: EguiContexts,
+    mut brush: ResMut<Brush>,
+    mut history: ResMut<EditHistory>,
+    mut edits: TileEdits,
+    action_query: Query<&ActionState<EditorAction>>,
+) {
+    let mut undo = false;
+    let mut redo = false;
+    egui::Window::new("Editor")
+        .default_open(false)
+        .resizable(false)
+        .anchor(Align2::LEFT_TOP, egui::Vec2::new(5.0, 150.0))
+        .show(contexts.ctx_mut(), |ui| {
+            for tool in Tool::ALL {
+                ui.radio_value(&mut brush.tool, tool, tool.name());
+            }
+            ui.separator();
+
+            match brush.tool {
+                Tool::PaintTileType => {
+                    let mut tile_type = brush.tile_type;
+                    ComboBox::from_label("Tile Type")
+                        .selected_text(format!("{tile_type:?}"))
+                        .show_ui(ui, |ui| {
+                            for option in TileType::ALL {
+                                ui.selectable_value(&mut tile_type, option, format!("{option:?}"));
+                            }
+                        });
+                    brush.tile_type = tile_type;
+                }
+                Tool::PlaceDecoration => {
+                    let mut decoration = brush.decoration;
+                    ComboBox::from_label("Decoration")
+                        .selected_text(format!("{decoration:?}"))
+                        .show_ui(ui, |ui| {
+                            for option in Decoration::ALL {
+                                ui.selectable_value(&mut decoration, option, format!("{option:?}"));
+                            }
+                        });
+                    brush.decoration = decoration;
+                }
+                _ => {}
+            }
+            egui::Slider::new(&mut brush.radius, 0..=20)
+                .text("Radius")
+                .ui(ui);
+            egui::Slider::new(&mut brush.falloff, 0.0..=1.0)
+                .text("Falloff")
+                .ui(ui);
+            egui::Slider::new(&mut brush.strength, 0.05..=1.0)
+                .text("Strength")
+                .ui(ui);
+            ui.separator();
+
+            ui.horizontal(|ui| {
+                undo = ui
+                    .add_enabled(!history.undo.is_empty(), egui::Button::new("Undo"))
+                    .clicked();
+                redo = ui
+                    .add_enabled(!history.redo.is_empty(), egui::Button::new("Redo"))
+                    .clicked();
+            });
+        });
+
+    // Shortcuts are left to text fields while one of them has focus, and to the settings history
+    // while no painting tool is selected.
+    if brush.is_editing() && !contexts.ctx_mut().wants_keyboard_input() {
+        if let Ok(action_state) = action_query.get_single() {
+            undo |= action_state.just_pressed(EditorAction::Undo);
+            redo |= action_state.just_pressed(EditorAction::Redo);
+        }
+    }
+
+    // Undoing in the middle of a stroke would leave it with outdated deltas.
+    if history.stroke.is_some() {
+        return;
+    }
+    if undo {
+        edits.undo(&mut history);
+    } else if redo {
+        edits.redo(&mut history);
+    }
+}
+
+fn paint_system(
+    time: Res<Time>,
+    cursor_pos: Res<CursorPos>,
+    brush: Res<Brush>,
+    mut history: ResMut<EditHistory>,
+    mut edits: TileEdits,
+    mut contexts: EguiContexts,
+    action_query: Query<&ActionState<EditorAction>>,
+) {
+    let Ok(action_state) = action_query.get_single() else {
+        return;
+    };
+    let center = world_to_tile_pos(cursor_pos.world);
+
+    if action_state.just_released(EditorAction::Paint) {
+        let Some(stroke) = history.stroke.take() else {
+            return;
+        };
+        let tiles: Vec<_> = stroke
+            .before
+            .into_iter()
+            .map(|(pos, before)| (pos, before, edits.deltas.get(pos)))
+            .filter(|(_, before, after)| before != after)
+            .collect();
+        if !tiles.is_empty() {
+            history.undo.push(Edit { tiles });
+            history.redo.clear();
+        }
+        return;
+    }
+
+    if !action_state.pressed(EditorAction::Paint) || !brush.is_editing() {
+        return;
+    }
+    if history.stroke.is_none() {
+        // Clicks on the UI shouldn't paint the map below it.
+        if !action_state.just_pressed(EditorAction::Paint)
+            || contexts.ctx_mut().is_pointer_over_area()
+        {
+            return;
+        }
+        history.stroke = Some(Stroke {
+            before: HashMap::new(),
+            flatten_height: edits.deltas.tile_data(&edits.noise, center).height,
+        });
+    }
+
+    let padding = IVec2::splat(brush.radius as i32 + 1);
+    let mut tiles = edits
+        .noise
+        .get_tile_data_grid(center - padding, (padding * 2 + IVec2::ONE).as_uvec2());
+    edits.deltas.apply(&mut tiles);
+
+    let Some(stroke) = history.stroke.as_mut() else {
+        return;
+    };
+    let changes = brush.apply(
+        center,
+        &tiles,
+        &edits.deltas,
+        stroke,
+        time.delta_seconds(),
+        edits.noise.seed(),
+    );
+    for (pos, _) in &changes {
+        stroke
+            .before
+            .entry(*pos)
+            .or_insert_with(|| edits.deltas.get(*pos));
+    }
+    edits.set(changes);
+}
+
+fn draw_brush(mut gizmos: Gizmos, brush: Res<Brush>, cursor_pos: Res<CursorPos>) {
+    if !brush.is_editing() {
+        return;
+    }
+    let center = coords::tile_to_world_pos(world_to_tile_pos(cursor_pos.world));
+    let radius = (brush.radius as f32 + 0.5) * TILE_SIZE.x;
+    gizmos.circle_2d(center, radius, Color::WHITE);
+    if brush.falloff > 0.0 {
+        gizmos.circle_2d(center, radius * (1.0 - brush.falloff), Color::GRAY);
+    }
+}
+
+fn default_input_map_editor() -> InputMap<EditorAction> {
+    let mut input_map = InputMap::default();
+    input_map.insert(MouseButton::Left, EditorAction::Paint);
+
+    for modifier in [
+        KeyCode::ControlLeft,
+        KeyCode::ControlRight,
+        KeyCode::SuperLeft,
+    ] {
+        input_map.insert(UserInput::chord([modifier, KeyCode::Z]), EditorAction::Undo);
+        input_map.insert(UserInput::chord([modifier, KeyCode::Y]), EditorAction::Redo);
+        input_map.insert(
+            UserInput::chord([modifier, KeyCode::ShiftLeft, KeyCode::Z]),
+            EditorAction::Redo,
+        );
+    }
+
+    input_map
+}
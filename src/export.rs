@@ -0,0 +1,168 @@
+use crate::autotile::Tileset;
+use crate::coords;
+use crate::game_map::{ChunkSamples, TilePainter, CHUNK_SIZE, TILE_SIZE};
+use crate::tile_data::Decoration;
+use crate::view_mode::{self, ViewMode};
+use bevy::prelude::*;
+use image::{ImageResult, Rgba, RgbaImage};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+const ASSET_DIR: &str = "assets";
+// Images are allocated in one piece, this is 256 MiB of RGBA.
+pub const MAX_IMAGE_PIXELS: u64 = 1 << 26;
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Serialize, clap::ValueEnum)]
+pub enum ExportLayer {
+    TileTypes,
+    Height,
+    Humidity,
+}
+
+impl ExportLayer {
+    pub const ALL: [ExportLayer; 3] = [
+        ExportLayer::TileTypes,
+        ExportLayer::Height,
+        ExportLayer::Humidity,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        self.view_mode().name()
+    }
+
+    pub fn view_mode(&self) -> ViewMode {
+        match self {
+            ExportLayer::TileTypes => ViewMode::TileTypes,
+            ExportLayer::Height => ViewMode::Height,
+            ExportLayer::Humidity => ViewMode::Humidity,
+        }
+    }
+}
+
+// A rectangle of world tiles, origin being the bottom left tile.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct TileRegion {
+    pub origin: IVec2,
+    pub size: UVec2,
+}
+
+impl TileRegion {
+    pub fn contains(&self, world_tile_pos: IVec2) -> bool {
+        let local = world_tile_pos - self.origin;
+        local.x >= 0 && local.y >= 0 && local.x < self.size.x as i32 && local.y < self.size.y as i32
+    }
+
+    fn chunks(&self) -> impl Iterator<Item = IVec2> {
+        let min = coords::tile_to_chunk_pos(self.origin);
+        let max = coords::tile_to_chunk_pos(self.origin + self.size.as_ivec2() - IVec2::ONE);
+        (min.y..=max.y).flat_map(move |y| (min.x..=max.x).map(move |x| IVec2::new(x, y)))
+    }
+}
+
+// One pixel per tile, or a whole tile sprite.
+pub fn pixels_per_tile(sprites: bool) -> u32 {
+    if sprites {
+        TILE_SIZE.x as u32
+    } else {
+        1
+    }
+}
+
+// Fails for regions whose export would be too large to allocate, before anything is sampled.
+pub fn check_image_size(region: TileRegion, sprites: bool) -> Result<(), String> {
+    let pixels_per_tile = pixels_per_tile(sprites) as u64;
+    let pixels = region.size.x as u64 * region.size.y as u64 * pixels_per_tile * pixels_per_tile;
+    if pixels > MAX_IMAGE_PIXELS {
+        return Err(format!(
+            "{}x{} tiles would be {pixels} pixels, exports are limited to {MAX_IMAGE_PIXELS}",
+            region.size.x, region.size.y
+        ));
+    }
+    Ok(())
+}
+
+pub fn tileset_image_path(tileset: &Tileset) -> PathBuf {
+    Path::new(ASSET_DIR).join(&tileset.texture)
+}
+
+pub fn decoration_image_path(decoration: Decoration) -> PathBuf {
+    Path::new(ASSET_DIR).join(decoration.texture())
+}
+
+pub fn load_tileset_image(tileset: &Tileset) -> ImageResult<RgbaImage> {
+    Ok(image::open(tileset_image_path(tileset))?.into_rgba8())
+}
+
+// Samples the region chunk by chunk, so painting works the same way as for spawned chunks.
+pub fn for_each_tile(
+    painter: &TilePainter,
+    region: TileRegion,
+    mut f: impl FnMut(IVec2, &ChunkSamples),
+) {
+    for chunk_pos in region.chunks() {
+        let samples = painter.sample_chunk(chunk_pos);
+        let chunk_origin = coords::chunk_origin(chunk_pos);
+        for y in 0..CHUNK_SIZE.y as i32 {
+            for x in 0..CHUNK_SIZE.x as i32 {
+                let world_tile_pos = chunk_origin + IVec2::new(x, y);
+                if region.contains(world_tile_pos) {
+                    f(world_tile_pos, &samples);
+                }
+            }
+        }
+    }
+}
+
+// Renders one pixel per tile, or the same tile sprites as in game if a tileset image is given.
+// North ends up at the top of the image.
+pub fn render_region(
+    painter: &TilePainter,
+    region: TileRegion,
+    tileset_image: Option<&RgbaImage>,
+) -> RgbaImage {
+    let pixels_per_tile = pixels_per_tile(tileset_image.is_some());
+    let mut image = RgbaImage::new(
+        region.size.x * pixels_per_tile,
+        region.size.y * pixels_per_tile,
+    );
+
+    for_each_tile(painter, region, |world_tile_pos, samples| {
+        let local = (world_tile_pos - region.origin).as_uvec2();
+        let pixel_x = local.x * pixels_per_tile;
+        let pixel_y = (region.size.y - 1 - local.y) * pixels_per_tile;
+        match tileset_image {
+            None => {
+                let color = match painter.view_mode.value(samples, world_tile_pos) {
+                    Some(value) => view_mode::heatmap(value),
+                    None => samples
+                        .tiles
+                        .get(world_tile_pos)
+                        .get_tile_type()
+                        .get_map_color(),
+                };
+                image.put_pixel(pixel_x, pixel_y, Rgba(color.as_rgba_u8()));
+            }
+            Some(tileset_image) => {
+                let (texture_index, color) = painter.paint(samples, world_tile_pos);
+                let columns = painter.tileset.columns;
+                let source_x = texture_index.0 % columns * pixels_per_tile;
+                let source_y = texture_index.0 / columns * pixels_per_tile;
+                let [r, g, b, a] = color.0.as_rgba_f32();
+                for dy in 0..pixels_per_tile {
+                    for dx in 0..pixels_per_tile {
+                        let source = tileset_image.get_pixel(source_x + dx, source_y + dy);
+                        let tinted = Rgba([
+                            (source[0] as f32 * r) as u8,
+                            (source[1] as f32 * g) as u8,
+                            (source[2] as f32 * b) as u8,
+                            (source[3] as f32 * a) as u8,
+                        ]);
+                        image.put_pixel(pixel_x + dx, pixel_y + dy, tinted);
+                    }
+                }
+            }
+        }
+    });
+
+    image
+}
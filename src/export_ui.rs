@@ -0,0 +1,280 @@
+use crate::autotile::Tileset;
+use crate::coords;
+use crate::data_export::{self, DataFormat};
+use crate::export::{self, ExportLayer, TileRegion};
+use crate::game_map::{PainterParams, TilePainter, TileRenderSettings};
+use crate::game_ui::RegenerateMapEvent;
+use crate::noise_generator::NoiseGenerator;
+use crate::tile_deltas::TileDeltas;
+use crate::tiled::{self, TiledFormat};
+use bevy::prelude::*;
+use bevy::tasks::{block_on, futures_lite::future, AsyncComputeTaskPool, Task};
+use bevy_egui::egui::{Align2, ComboBox, DragValue, Grid};
+use bevy_egui::*;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+
+// Exports write straight to the file system, so this isn't available in the browser.
+pub struct ExportUIPlugin;
+impl Plugin for ExportUIPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ExportSettings>()
+            .add_event::<ImportTiledEvent>()
+            .add_systems(Update, export_ui_system)
+            .add_systems(Update, import_tiled_system);
+    }
+}
+
+// Importing needs write access to the deltas the painter borrows, so it runs in its own system.
+#[derive(Event)]
+struct ImportTiledEvent(PathBuf);
+
+#[derive(Resource)]
+struct ExportSettings {
+    region: TileRegion,
+    layer: ExportLayer,
+    // Raw values instead of an image, only for height and humidity.
+    data_format: Option<DataFormat>,
+    sprites: bool,
+    path: String,
+    status: String,
+    // The running export, whose result replaces the status.
+    task: Option<Task<String>>,
+}
+impl Default for ExportSettings {
+    fn default() -> Self {
+        ExportSettings {
+            region: TileRegion {
+                origin: IVec2::new(-128, -128),
+                size: UVec2::new(256, 256),
+            },
+            layer: ExportLayer::Height,
+            data_format: None,
+            sprites: false,
+            path: String::from("export.png"),
+            status: String::new(),
+            task: None,
+        }
+    }
+}
+
+fn export_ui_system(
+    mut contexts: EguiContexts,
+    mut settings: ResMut<ExportSettings>,
+    painter_params: PainterParams,
+    camera_query: Query<(&Transform, &OrthographicProjection), With<Camera2d>>,
+    mut import_tiled_event: EventWriter<ImportTiledEvent>,
+) {
+    if let Some(task) = settings.task.as_mut() {
+        if let Some(status) = block_on(future::poll_once(task)) {
+            settings.status = status;
+            settings.task = None;
+        }
+    }
+
+    let mut export = false;
+    let mut import = false;
+    let mut use_camera_view = false;
+    egui::Window::new("Export")
+        .default_open(false)
+        .resizable(false)
+        .anchor(Align2::CENTER_BOTTOM, egui::Vec2::new(0.0, -5.0))
+        .show(contexts.ctx_mut(), |ui| {
+            Grid::new("export").show(ui, |ui| {
+                ui.label("Origin:");
+                ui.horizontal(|ui| {
+                    ui.add(DragValue::new(&mut settings.region.origin.x));
+                    ui.add(DragValue::new(&mut settings.region.origin.y));
+                });
+                ui.end_row();
+
+                ui.label("Size:");
+                ui.horizontal(|ui| {
+                    ui.add(DragValue::new(&mut settings.region.size.x).clamp_range(1..=8192));
+                    ui.add(DragValue::new(&mut settings.region.size.y).clamp_range(1..=8192));
+                });
+                ui.end_row();
+
+                ui.label("Layer:");
+                let mut layer = settings.layer;
+                ComboBox::from_id_source("export_layer")
+                    .selected_text(layer.name())
+                    .show_ui(ui, |ui| {
+                        for option in ExportLayer::ALL {
+                            ui.selectable_value(&mut layer, option, option.name());
+                        }
+                    });
+                settings.layer = layer;
+                ui.end_row();
+
+                ui.label("Format:");
+                let format_name = |format: Option<DataFormat>| match format {
+                    Some(format) => format.name(),
+                    None => "Image",
+                };
+                let mut data_format = settings.data_format;
+                ComboBox::from_id_source("export_data_format")
+                    .selected_text(format_name(data_format))
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut data_format, None, format_name(None));
+                        for option in DataFormat::ALL {
+                            ui.selectable_value(&mut data_format, Some(option), option.name());
+                        }
+                    });
+                if data_format != settings.data_format {
+                    let extension = data_format.map_or("png", |format| format.extension());
+                    settings.path = Path::new(&settings.path)
+                        .with_extension(extension)
+                        .to_string_lossy()
+                        .into_owned();
+                    settings.data_format = data_format;
+                    // Tile types have no raw values to export.
+                    if data_format.is_some() && settings.layer == ExportLayer::TileTypes {
+                        settings.layer = ExportLayer::Height;
+                    }
+                }
+                ui.end_row();
+
+                ui.label("Tile Sprites:");
+                ui.checkbox(&mut settings.sprites, "");
+                ui.end_row();
+
+                ui.label("File:");
+                ui.text_edit_singleline(&mut settings.path)
+                    .on_hover_text("Use .tmx or .tmj to export a map for the Tiled editor");
+                ui.end_row();
+            });
+
+            let is_tiled = TiledFormat::from_path(Path::new(&settings.path)).is_some();
+            ui.horizontal(|ui| {
+                use_camera_view = ui.button("Use Camera View").clicked();
+                export = ui
+                    .add_enabled(settings.task.is_none(), egui::Button::new("Export"))
+                    .clicked();
+                import = ui
+                    .add_enabled(is_tiled, egui::Button::new("Import Tiled"))
+                    .clicked();
+            });
+            if !settings.status.is_empty() {
+                ui.label(&settings.status);
+            }
+        });
+
+    if use_camera_view {
+        if let Ok((transform, projection)) = camera_query.get_single() {
+            let camera_pos = transform.translation.xy();
+            let (min, max) = coords::tiles_overlapping(Rect {
+                min: camera_pos + projection.area.min,
+                max: camera_pos + projection.area.max,
+            });
+            settings.region = TileRegion {
+                origin: min,
+                size: (max - min + IVec2::ONE).as_uvec2(),
+            };
+        }
+    }
+
+    if import {
+        import_tiled_event.send(ImportTiledEvent(PathBuf::from(&settings.path)));
+    }
+
+    if export {
+        let painter = painter_params.painter();
+        let job = ExportJob {
+            noise: painter.noise.clone(),
+            tileset: painter.tileset.clone(),
+            render_settings: painter.render_settings.clone(),
+            deltas: painter.deltas.clone(),
+            region: settings.region,
+            layer: settings.layer,
+            data_format: settings.data_format,
+            sprites: settings.sprites,
+            path: PathBuf::from(&settings.path),
+        };
+        settings.status = format!("Exporting to {}...", settings.path);
+        settings.task = Some(AsyncComputeTaskPool::get().spawn(async move { job.run() }));
+    }
+}
+
+// Everything an export needs, owned so it can run on the async compute pool instead of
+// freezing the game while large regions are sampled and encoded.
+struct ExportJob {
+    noise: NoiseGenerator,
+    tileset: Tileset,
+    render_settings: TileRenderSettings,
+    deltas: TileDeltas,
+    region: TileRegion,
+    layer: ExportLayer,
+    data_format: Option<DataFormat>,
+    sprites: bool,
+    path: PathBuf,
+}
+
+impl ExportJob {
+    // Returns the status to show.
+    fn run(&self) -> String {
+        match self.export() {
+            Ok(status) => status,
+            Err(error) => format!("Export failed: {error}"),
+        }
+    }
+
+    fn export(&self) -> Result<String, Box<dyn Error>> {
+        let painter = TilePainter {
+            noise: &self.noise,
+            tileset: &self.tileset,
+            render_settings: &self.render_settings,
+            deltas: &self.deltas,
+            view_mode: self.layer.view_mode(),
+        };
+        let path = self.path.as_path();
+        if TiledFormat::from_path(path).is_some() {
+            export::check_image_size(self.region, false)?;
+            tiled::export_tiled(&painter, self.region, path)?;
+            return Ok(format!("Exported to {}", path.display()));
+        }
+        if let Some(format) = self.data_format {
+            export::check_image_size(self.region, false)?;
+            let sidecar_path =
+                data_export::export_data(&self.noise, self.region, self.layer, format, path)?;
+            return Ok(format!(
+                "Exported to {}, metadata in {}",
+                path.display(),
+                sidecar_path.display()
+            ));
+        }
+
+        export::check_image_size(self.region, self.sprites)?;
+        let tileset_image = if self.sprites {
+            let image = export::load_tileset_image(&self.tileset)
+                .map_err(|error| format!("Failed to load tileset: {error}"))?;
+            Some(image)
+        } else {
+            None
+        };
+        export::render_region(&painter, self.region, tileset_image.as_ref()).save(path)?;
+        Ok(format!("Exported to {}", path.display()))
+    }
+}
+
+fn import_tiled_system(
+    mut events: EventReader<ImportTiledEvent>,
+    mut settings: ResMut<ExportSettings>,
+    tileset: Res<Tileset>,
+    noise: Res<NoiseGenerator>,
+    mut deltas: ResMut<TileDeltas>,
+    mut regenerate_map_event: EventWriter<RegenerateMapEvent>,
+) {
+    for ImportTiledEvent(path) in events.read() {
+        match tiled::import_tiled(path, &tileset, &noise, &mut deltas) {
+            Ok(changed_tiles) => {
+                settings.status = format!(
+                    "Imported {changed_tiles} edited tiles from {}",
+                    path.display()
+                );
+                regenerate_map_event.send(RegenerateMapEvent);
+            }
+            Err(error) => settings.status = format!("Import failed: {error}"),
+        }
+    }
+}
@@ -1,16 +1,32 @@
 use crate::camera::CameraPlugin;
+use crate::editor::EditorPlugin;
 use crate::game_map::GameMapPlugin;
 use crate::game_ui::GameUIPlugin;
+use crate::lod::LodPlugin;
+use crate::minimap::MinimapPlugin;
+use crate::settings_history::SettingsHistoryPlugin;
 use bevy::prelude::*;
+use bevy_ecs_tilemap::TilemapPlugin;
 
 pub struct GamePlugin;
 impl Plugin for GamePlugin {
     fn build(&self, app: &mut App) {
+        // Only draws the tilemaps, so it's kept out of GameMapPlugin to let the map stream
+        // without a renderer.
         app.init_resource::<CursorPos>()
+            .add_plugins(TilemapPlugin)
             .add_plugins(GameMapPlugin)
             .add_plugins(CameraPlugin)
             .add_plugins(GameUIPlugin)
+            .add_plugins(MinimapPlugin)
+            .add_plugins(LodPlugin)
+            .add_plugins(EditorPlugin)
+            .add_plugins(SettingsHistoryPlugin)
             .add_systems(First, update_cursor_pos);
+
+        #[cfg(not(target_arch = "wasm32"))]
+        app.add_plugins(crate::export_ui::ExportUIPlugin)
+            .add_plugins(crate::world_save::WorldSavePlugin);
     }
 }
 
@@ -1,15 +1,36 @@
-use crate::game::CursorPos;
+use crate::autotile::{self, Tileset};
+use crate::chunk_cache::ChunkCache;
+use crate::chunk_terrain::ChunkTerrain;
+use crate::coords::{self, chunk_origin};
 use crate::game_ui::RegenerateMapEvent;
+use crate::lod;
 use crate::noise_generator::NoiseGenerator;
-use crate::tile_data::TileType;
+use crate::tile_data::{TerrainGrid, TileData, TileGrid};
+use crate::tile_deltas::TileDeltas;
+use crate::view_mode::{self, ViewMode};
+use bevy::ecs::system::SystemParam;
 use bevy::prelude::*;
+use bevy::tasks::{block_on, futures_lite::future, AsyncComputeTaskPool, Task};
+use bevy::utils::{Duration, Instant};
 use bevy_ecs_tilemap::prelude::*;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
 
 // Right now mostly sticking to the example code found at https://github.com/divark/bevy_ecs_tilemap/blob/0.12-fixes/examples/
 
-const TILE_SIZE: TilemapTileSize = TilemapTileSize { x: 16.0, y: 16.0 };
+pub const TILE_SIZE: TilemapTileSize = TilemapTileSize { x: 16.0, y: 16.0 };
 pub const CHUNK_SIZE: UVec2 = UVec2 { x: 32, y: 32 };
+// Chunks generated in the background at the same time, so zooming out doesn't queue up work for
+// chunks the camera has already left again.
+const MAX_PENDING_CHUNKS: usize = 32;
+// How far ahead of a moving camera chunks are prefetched, in seconds of movement. Capped at one
+// chunk so prefetched chunks are still within the range that keeps them alive.
+const PREFETCH_SECONDS: f32 = 0.5;
+// How much closer chunks in the direction of movement count, 1.0 would make distance ahead free.
+const AHEAD_WEIGHT: f32 = 0.5;
+// Chunks repainted per frame after the generator changed in place, so a live preview doesn't
+// stall the frame it was applied in.
+const STALE_CHUNKS_PER_FRAME: usize = 2;
 const RENDER_CHUNK_SIZE: UVec2 = UVec2 {
     x: CHUNK_SIZE.x * 2,
     y: CHUNK_SIZE.y * 2,
@@ -24,66 +45,324 @@ impl Plugin for GameMapPlugin {
                 ..Default::default()
             })
             .insert_resource(NoiseGenerator::default())
-            .add_plugins(TilemapPlugin)
+            .init_resource::<Tileset>()
+            .init_resource::<TileRenderSettings>()
+            .init_resource::<ViewMode>()
+            .init_resource::<TileDeltas>()
+            .init_resource::<ChunkStreamingSettings>()
             .add_systems(Update, spawn_chunks_around_camera)
+            .add_systems(Update, spawn_generated_chunks)
             .add_systems(Update, despawn_out_of_range_chunks)
-            .add_systems(Update, highlight_tile_below_cursor)
+            .add_systems(Update, repaint_tiles_event_listener)
+            .add_systems(
+                Update,
+                (
+                    repaint_map_event_listener,
+                    repaint_chunks_on_view_mode_change,
+                    repaint_stale_chunks,
+                )
+                    .chain()
+                    .before(despawn_out_of_range_chunks),
+            )
+            .add_event::<RepaintTilesEvent>()
+            .add_event::<RepaintMapEvent>()
             .add_systems(First, regenerate_map_event_listener);
     }
 }
 
-#[derive(Component)]
-pub struct HighlightedTile;
 #[derive(Component)]
 pub struct ChunkData {
     pub position: IVec2,
 }
 
-fn tile_type_to_texture_index(tile_type: &TileType) -> TileTextureIndex {
-    match tile_type {
-        TileType::Water => TileTextureIndex(0),
-        TileType::Sand => TileTextureIndex(1),
-        TileType::Grass => TileTextureIndex(2),
-        TileType::Stone => TileTextureIndex(3),
-        TileType::DeepWater => TileTextureIndex(4),
+// Decoration sprites are children of the chunk's tilemap, listed here so they can be replaced
+// without touching the tiles.
+#[derive(Component, Default)]
+struct ChunkDecorations(Vec<Entity>);
+
+// Samples a chunk was last painted from, taken out and moved into the cache when it's despawned.
+#[derive(Component)]
+struct PaintedSamples(Option<ChunkSamples>);
+
+// Asks for already spawned tiles within min..=max to be painted again, e.g. after editing them.
+#[derive(Event)]
+pub struct RepaintTilesEvent {
+    pub min: IVec2,
+    pub max: IVec2,
+}
+
+// Asks for every spawned chunk to be painted again from the current generator, keeping the
+// entities around instead of respawning them like RegenerateMapEvent does.
+#[derive(Event)]
+pub struct RepaintMapEvent;
+
+// Chunks still showing the previous generator, repainted a few at a time starting at the camera.
+#[derive(Component)]
+struct StaleChunk;
+
+// Decorations further south are drawn on top, so trees overlap the ones behind them.
+const DECORATION_Z: f32 = 500.0;
+const DECORATION_Z_PER_TILE: f32 = 0.001;
+
+#[derive(Resource)]
+pub struct ChunkStreamingSettings {
+    // Main thread time per frame spent turning generated chunks into tilemaps, in milliseconds.
+    // At least one chunk is spawned per frame no matter how low this is.
+    pub frame_budget_ms: f32,
+    // Chunks beyond the visible ones that are kept before despawning them, so moving back and
+    // forth across a chunk border doesn't respawn the chunks behind it. At least one, otherwise
+    // prefetched chunks would be despawned right away.
+    pub despawn_margin: u32,
+    // Samples of this many recently despawned chunks are kept around, 0 turns the cache off.
+    pub cache_size: usize,
+}
+impl Default for ChunkStreamingSettings {
+    fn default() -> Self {
+        ChunkStreamingSettings {
+            frame_budget_ms: 4.0,
+            despawn_margin: 2,
+            cache_size: 64,
+        }
+    }
+}
+
+#[derive(Resource, Clone)]
+pub struct TileRenderSettings {
+    pub variants: bool,
+    pub tint: bool,
+    pub shading: bool,
+    // Compass direction the light is coming from, in degrees clockwise from north.
+    pub light_azimuth: f32,
+    // Angle of the light above the horizon, in degrees.
+    pub light_elevation: f32,
+    // Height exaggeration applied before computing slopes.
+    pub relief: f32,
+}
+impl Default for TileRenderSettings {
+    fn default() -> Self {
+        TileRenderSettings {
+            variants: true,
+            tint: false,
+            shading: false,
+            light_azimuth: 315.0,
+            light_elevation: 45.0,
+            relief: 20.0,
+        }
     }
 }
+impl TileRenderSettings {
+    // 1.0 on flat ground, darker on slopes facing away from the light.
+    pub fn hillshade(&self, height_gradient: Vec2) -> f32 {
+        let azimuth = self.light_azimuth.to_radians();
+        let elevation = self.light_elevation.to_radians();
+        let light = Vec3::new(
+            azimuth.sin() * elevation.cos(),
+            azimuth.cos() * elevation.cos(),
+            elevation.sin(),
+        );
+        let normal = (-height_gradient * self.relief).extend(1.0).normalize();
+        (normal.dot(light) / light.z).clamp(0.0, 1.0)
+    }
+
+    fn tile_color(&self, tile_data: &TileData, height_gradient: Vec2) -> TileColor {
+        let mut color = Color::WHITE;
+        if self.tint {
+            color = tile_data.get_tint();
+        }
+        if self.shading {
+            let shaded = tile_data.get_color(self.hillshade(height_gradient));
+            color = color * Vec4::from(shaded.as_rgba_f32());
+        }
+        TileColor(color)
+    }
+}
+
+// Noise samples covering a chunk plus enough padding for everything painted on top of it.
+pub struct ChunkSamples {
+    pub tiles: TerrainGrid,
+    pub river_flow: Option<TileGrid<f32>>,
+}
+
+// Origin and size of the tiles sampled for a chunk, including its padding.
+fn sample_area(view_mode: ViewMode, chunk_pos: IVec2) -> (IVec2, UVec2) {
+    let padding = view_mode.sample_padding();
+    (
+        chunk_origin(chunk_pos) - IVec2::splat(padding as i32),
+        CHUNK_SIZE + UVec2::splat(padding * 2),
+    )
+}
+
+// Doesn't touch the ECS, so it can run on a background thread.
+pub fn sample_chunk(
+    noise: &NoiseGenerator,
+    deltas: &TileDeltas,
+    view_mode: ViewMode,
+    chunk_pos: IVec2,
+) -> ChunkSamples {
+    let (origin, size) = sample_area(view_mode, chunk_pos);
+    let mut tiles = noise.get_tile_data_grid(origin, size);
+    deltas.apply(&mut tiles);
+    let river_flow = match view_mode {
+        ViewMode::RiverFlow => Some(view_mode::river_flow(tiles.height())),
+        _ => None,
+    };
+
+    ChunkSamples { tiles, river_flow }
+}
+
+// Everything that decides how a tile looks, bundled so spawning and repainting stay in sync.
+pub struct TilePainter<'a> {
+    pub noise: &'a NoiseGenerator,
+    pub tileset: &'a Tileset,
+    pub render_settings: &'a TileRenderSettings,
+    pub deltas: &'a TileDeltas,
+    pub view_mode: ViewMode,
+}
+impl TilePainter<'_> {
+    pub fn sample_chunk(&self, chunk_pos: IVec2) -> ChunkSamples {
+        sample_chunk(self.noise, self.deltas, self.view_mode, chunk_pos)
+    }
+
+    pub fn paint(
+        &self,
+        samples: &ChunkSamples,
+        world_tile_pos: IVec2,
+    ) -> (TileTextureIndex, TileColor) {
+        if let Some(value) = self.view_mode.value(samples, world_tile_pos) {
+            return (
+                self.tileset.overlay_texture_index(),
+                TileColor(view_mode::heatmap(value)),
+            );
+        }
+
+        let tile_data = samples.tiles.get(world_tile_pos);
+        let tile_type = tile_data.get_tile_type();
+        let neighbour_mask = autotile::neighbour_mask(tile_type, world_tile_pos, |pos| {
+            samples.tiles.get(pos).get_tile_type()
+        });
+        let texture_index = if self.render_settings.variants {
+            let tile_hash = autotile::tile_hash(world_tile_pos, self.noise.seed());
+            self.tileset
+                .variant_texture_index(tile_type, neighbour_mask, tile_hash)
+        } else {
+            self.tileset.texture_index(tile_type, neighbour_mask)
+        };
+        let color = self
+            .render_settings
+            .tile_color(&tile_data, samples.tiles.height_gradient(world_tile_pos));
+
+        (texture_index, color)
+    }
+}
+
+#[derive(SystemParam)]
+pub struct PainterParams<'w> {
+    pub noise: Res<'w, NoiseGenerator>,
+    pub tileset: Res<'w, Tileset>,
+    pub render_settings: Res<'w, TileRenderSettings>,
+    pub deltas: Res<'w, TileDeltas>,
+    pub view_mode: Res<'w, ViewMode>,
+}
+impl PainterParams<'_> {
+    pub fn painter(&self) -> TilePainter {
+        TilePainter {
+            noise: &self.noise,
+            tileset: &self.tileset,
+            render_settings: &self.render_settings,
+            deltas: &self.deltas,
+            view_mode: *self.view_mode,
+        }
+    }
+}
+
+fn spawn_decorations(
+    commands: &mut Commands,
+    asset_server: &AssetServer,
+    tilemap_entity: Entity,
+    chunk_pos: IVec2,
+    samples: &ChunkSamples,
+) -> ChunkDecorations {
+    let mut decorations = ChunkDecorations::default();
+    let chunk_origin = chunk_origin(chunk_pos);
+    for y in 0..CHUNK_SIZE.y as i32 {
+        for x in 0..CHUNK_SIZE.x as i32 {
+            let world_tile_pos = chunk_origin + IVec2::new(x, y);
+            let Some(decoration) = samples.tiles.get(world_tile_pos).decoration else {
+                continue;
+            };
+            let translation = Vec3::new(
+                x as f32 * TILE_SIZE.x,
+                (y as f32 - 0.5) * TILE_SIZE.y,
+                DECORATION_Z - world_tile_pos.y as f32 * DECORATION_Z_PER_TILE,
+            );
+            let entity = commands
+                .spawn(SpriteBundle {
+                    texture: asset_server.load(decoration.texture()),
+                    sprite: Sprite {
+                        custom_size: Some(decoration.size()),
+                        anchor: bevy::sprite::Anchor::BottomCenter,
+                        ..default()
+                    },
+                    transform: Transform::from_translation(translation),
+                    ..default()
+                })
+                .id();
+            commands.entity(tilemap_entity).add_child(entity);
+            decorations.0.push(entity);
+        }
+    }
+    decorations
+}
+
+fn respawn_decorations(
+    commands: &mut Commands,
+    asset_server: &AssetServer,
+    tilemap_entity: Entity,
+    chunk_pos: IVec2,
+    samples: &ChunkSamples,
+    decorations: &mut ChunkDecorations,
+) {
+    for decoration in decorations.0.drain(..) {
+        commands.entity(decoration).despawn_recursive();
+    }
+    *decorations = spawn_decorations(commands, asset_server, tilemap_entity, chunk_pos, samples);
+}
 
 fn spawn_chunk(
     commands: &mut Commands,
     asset_server: &AssetServer,
     chunk_pos: IVec2,
-    noise: &Res<NoiseGenerator>,
+    samples: ChunkSamples,
+    painter: &TilePainter,
 ) {
     let tilemap_entity = commands.spawn_empty().id();
     let mut tile_storage = TileStorage::empty(CHUNK_SIZE.into());
 
+    let chunk_origin = chunk_origin(chunk_pos);
     for x in 0..CHUNK_SIZE.x {
         for y in 0..CHUNK_SIZE.y {
             let tile_pos = TilePos { x, y };
-            let tile_data = noise.get_tile_data(chunk_pos, x, y);
-            let tile_type = tile_data.get_tile_type();
+            let world_tile_pos = chunk_origin + IVec2::new(x as i32, y as i32);
+            let (texture_index, color) = painter.paint(samples, world_tile_pos);
             let tile_entity = commands
                 .spawn(TileBundle {
                     position: tile_pos,
                     tilemap_id: TilemapId(tilemap_entity),
-                    texture_index: tile_type_to_texture_index(&tile_type),
+                    texture_index,
+                    color,
                     ..Default::default()
                 })
-                .insert(tile_data)
                 .id();
             commands.entity(tilemap_entity).add_child(tile_entity);
             tile_storage.set(&tile_pos, tile_entity);
         }
     }
 
-    let transform = Transform::from_translation(Vec3::new(
-        chunk_pos.x as f32 * CHUNK_SIZE.x as f32 * TILE_SIZE.x,
-        chunk_pos.y as f32 * CHUNK_SIZE.y as f32 * TILE_SIZE.y,
-        0.0,
-    ));
+    let transform = Transform::from_translation(coords::chunk_to_world_pos(chunk_pos).extend(0.0));
 
-    let tile_texture: Handle<Image> = asset_server.load("sprites/tiles.png");
+    let decorations =
+        spawn_decorations(commands, asset_server, tilemap_entity, chunk_pos, &samples);
+    let tile_texture: Handle<Image> = asset_server.load(&painter.tileset.texture);
     commands
         .entity(tilemap_entity)
         .insert(TilemapBundle {
@@ -97,74 +376,259 @@ fn spawn_chunk(
         })
         .insert(ChunkData {
             position: chunk_pos,
-        });
+        })
+        .insert(ChunkTerrain::new(chunk_pos, &samples.tiles))
+        .insert(decorations)
+        .insert(PaintedSamples(Some(samples)));
+}
+
+type PaintedTileQuery<'w, 's> =
+    Query<'w, 's, (&'static mut TileTextureIndex, &'static mut TileColor)>;
+
+// Spawned chunks along with everything that changes when their tiles are edited.
+type EditableChunkQuery<'w, 's, F = ()> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        &'static ChunkData,
+        &'static TileStorage,
+        &'static mut ChunkDecorations,
+        &'static mut ChunkTerrain,
+        &'static mut PaintedSamples,
+    ),
+    F,
+>;
+
+fn repaint_chunk(
+    samples: &ChunkSamples,
+    chunk_pos: IVec2,
+    tile_storage: &TileStorage,
+    painter: &TilePainter,
+    tiles_query: &mut PaintedTileQuery,
+) {
+    let chunk_origin = chunk_origin(chunk_pos);
+    for x in 0..CHUNK_SIZE.x {
+        for y in 0..CHUNK_SIZE.y {
+            let tile_pos = TilePos { x, y };
+            let Some(tile_entity) = tile_storage.get(&tile_pos) else {
+                continue;
+            };
+            if let Ok((mut texture_index, mut color)) = tiles_query.get_mut(tile_entity) {
+                let world_tile_pos = chunk_origin + IVec2::new(x as i32, y as i32);
+                (*texture_index, *color) = painter.paint(samples, world_tile_pos);
+            }
+        }
+    }
 }
 
-#[derive(Default, Debug, Resource)]
+// Where the camera is and where it's heading, used to decide which chunks to stream in first.
+#[derive(Default, Clone, Copy)]
+struct StreamingFocus {
+    // Camera position in chunks.
+    center: Vec2,
+    direction: Vec2,
+}
+
+impl StreamingFocus {
+    // Lower goes first: distance to the camera, minus a bonus for chunks in front of it.
+    fn priority(&self, chunk_pos: IVec2) -> f32 {
+        let offset = chunk_pos.as_vec2() + 0.5 - self.center;
+        offset.length() - offset.dot(self.direction) * AHEAD_WEIGHT
+    }
+
+    fn sort(&self, chunks: &mut [IVec2]) {
+        chunks.sort_by(|a, b| self.priority(*a).total_cmp(&self.priority(*b)));
+    }
+}
+
+// Chunks count as spawned as soon as their generation starts, so they aren't queued twice.
+#[derive(Default, Resource)]
 struct ChunkManager {
     pub spawned_chunks: HashSet<IVec2>,
+    // Samples still being generated on the async compute pool.
+    pending_chunks: HashMap<IVec2, Task<ChunkSamples>>,
+    // Generated samples waiting for frame budget to be turned into tilemaps.
+    ready_chunks: Vec<(IVec2, ChunkSamples)>,
+    focus: StreamingFocus,
+    cache: ChunkCache,
 }
 
-fn camera_pos_to_chunk_pos(camera_pos: &Vec2) -> IVec2 {
-    let camera_pos = camera_pos.as_ivec2();
-    let chunk_size: IVec2 = IVec2::new(CHUNK_SIZE.x as i32, CHUNK_SIZE.y as i32);
-    let tile_size: IVec2 = IVec2::new(TILE_SIZE.x as i32, TILE_SIZE.y as i32);
-    camera_pos / (chunk_size * tile_size)
+impl ChunkManager {
+    // Drops chunks that haven't been spawned yet and no longer match what should be shown, so
+    // they get generated again. Dropping a task cancels it.
+    fn cancel_pending(&mut self, mut cancel: impl FnMut(IVec2) -> bool) {
+        let spawned_chunks = &mut self.spawned_chunks;
+        let mut keep = |chunk_pos: &IVec2| {
+            if cancel(*chunk_pos) {
+                spawned_chunks.remove(chunk_pos);
+                false
+            } else {
+                true
+            }
+        };
+        self.pending_chunks.retain(|chunk_pos, _| keep(chunk_pos));
+        self.ready_chunks.retain(|(chunk_pos, _)| keep(chunk_pos));
+    }
+
+    // Like cancel_pending, but also forgets cached samples of those chunks.
+    fn invalidate(&mut self, mut invalid: impl FnMut(IVec2) -> bool) {
+        self.cancel_pending(&mut invalid);
+        self.cache.retain(|chunk_pos| !invalid(chunk_pos));
+    }
 }
 
-fn calculate_ideal_chunk_spawn_distance(camera_rect: &Rect) -> IVec2 {
-    IVec2::new(
-        (camera_rect.width() as i32 / (CHUNK_SIZE.x as i32 * TILE_SIZE.x as i32)) + 1,
-        (camera_rect.height() as i32 / (CHUNK_SIZE.y as i32 * TILE_SIZE.y as i32)) + 1,
-    )
+// Inclusive range of chunks the camera can see.
+fn visible_chunks(
+    camera_transform: &Transform,
+    projection: &OrthographicProjection,
+) -> (IVec2, IVec2) {
+    let camera_pos = camera_transform.translation.xy();
+    coords::chunks_overlapping(Rect {
+        min: camera_pos + projection.area.min,
+        max: camera_pos + projection.area.max,
+    })
 }
 
 fn spawn_chunks_around_camera(
-    mut commands: Commands,
-    asset_server: Res<AssetServer>,
+    time: Res<Time>,
     camera_query: Query<(&Transform, &OrthographicProjection), With<Camera2d>>,
     mut chunk_manager: ResMut<ChunkManager>,
-    noise: Res<NoiseGenerator>,
+    painter_params: PainterParams,
+    mut previous_camera_pos: Local<Option<Vec2>>,
 ) {
+    let task_pool = AsyncComputeTaskPool::get();
+    let chunk_world_size = CHUNK_SIZE.as_vec2() * coords::tile_size();
+    // Shared by all chunks started this frame instead of cloning the generator for each of them.
+    let mut noise: Option<Arc<NoiseGenerator>> = None;
     for (transform, projection) in camera_query.iter() {
-        let chunk_spawn_distance = calculate_ideal_chunk_spawn_distance(&projection.area);
-        let camera_chunk_pos = camera_pos_to_chunk_pos(&transform.translation.xy());
-        for y in (camera_chunk_pos.y - chunk_spawn_distance.y)
-            ..(camera_chunk_pos.y + chunk_spawn_distance.y)
-        {
-            for x in (camera_chunk_pos.x - chunk_spawn_distance.x)
-                ..(camera_chunk_pos.x + chunk_spawn_distance.x)
-            {
-                let chunk = IVec2::new(x, y);
-                if !chunk_manager.spawned_chunks.contains(&chunk) {
-                    chunk_manager.spawned_chunks.insert(chunk);
-                    spawn_chunk(&mut commands, &asset_server, chunk, &noise);
-                    return;
-                }
+        let camera_pos = transform.translation.xy();
+        let velocity = match *previous_camera_pos {
+            Some(previous) if time.delta_seconds() > 0.0 => {
+                (camera_pos - previous) / time.delta_seconds()
+            }
+            _ => Vec2::ZERO,
+        };
+        *previous_camera_pos = Some(camera_pos);
+        chunk_manager.focus = StreamingFocus {
+            center: coords::world_to_chunk_space(camera_pos),
+            direction: velocity.normalize_or_zero(),
+        };
+
+        // Zoomed out too far for tiles, the LOD chunks take over.
+        if lod::lod_level(projection).is_some() {
+            continue;
+        }
+
+        let (min, max) = visible_chunks(transform, projection);
+        let ahead = (velocity * PREFETCH_SECONDS / chunk_world_size)
+            .round()
+            .as_ivec2()
+            .clamp(IVec2::NEG_ONE, IVec2::ONE);
+        let min = min + ahead.min(IVec2::ZERO);
+        let max = max + ahead.max(IVec2::ZERO);
+        let mut missing_chunks: Vec<IVec2> = (min.y..=max.y)
+            .flat_map(|y| (min.x..=max.x).map(move |x| IVec2::new(x, y)))
+            .filter(|chunk| !chunk_manager.spawned_chunks.contains(chunk))
+            .collect();
+        chunk_manager.focus.sort(&mut missing_chunks);
+
+        for chunk in missing_chunks {
+            if chunk_manager.pending_chunks.len() >= MAX_PENDING_CHUNKS {
+                return;
+            }
+            chunk_manager.spawned_chunks.insert(chunk);
+            if let Some(samples) = chunk_manager.cache.take(chunk) {
+                chunk_manager.ready_chunks.push((chunk, samples));
+                continue;
+            }
+
+            let noise = noise
+                .get_or_insert_with(|| Arc::new(NoiseGenerator::clone(&painter_params.noise)))
+                .clone();
+            let view_mode = *painter_params.view_mode;
+            let (origin, size) = sample_area(view_mode, chunk);
+            let deltas = painter_params.deltas.region(origin, size);
+            let task =
+                task_pool.spawn(async move { sample_chunk(&noise, &deltas, view_mode, chunk) });
+            chunk_manager.pending_chunks.insert(chunk, task);
+        }
+    }
+}
+
+// Turns finished samples into tilemaps on the main thread, closest to the camera first, until
+// the frame budget is used up.
+fn spawn_generated_chunks(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    streaming_settings: Res<ChunkStreamingSettings>,
+    mut chunk_manager: ResMut<ChunkManager>,
+    painter_params: PainterParams,
+) {
+    let chunk_manager = &mut *chunk_manager;
+    let ready_chunks = &mut chunk_manager.ready_chunks;
+    chunk_manager.pending_chunks.retain(|chunk_pos, task| {
+        match block_on(future::poll_once(task)) {
+            Some(samples) => {
+                ready_chunks.push((*chunk_pos, samples));
+                false
             }
+            None => true,
+        }
+    });
+    if ready_chunks.is_empty() {
+        return;
+    }
+
+    // Sorted back to front, so the most important chunk can be popped off the end.
+    let focus = chunk_manager.focus;
+    ready_chunks.sort_by(|(a, _), (b, _)| focus.priority(*b).total_cmp(&focus.priority(*a)));
+
+    let start = Instant::now();
+    let budget = Duration::from_secs_f32(streaming_settings.frame_budget_ms / 1000.0);
+    let painter = painter_params.painter();
+    while let Some((chunk_pos, samples)) = ready_chunks.pop() {
+        spawn_chunk(&mut commands, &asset_server, chunk_pos, samples, &painter);
+        if start.elapsed() >= budget {
+            break;
         }
     }
 }
 
+// Despawns as many out of range chunks as fit in the frame budget, the rest follow next frame.
+// Their samples go into the cache, unless they were about to be repainted anyway.
 fn despawn_out_of_range_chunks(
     mut commands: Commands,
+    streaming_settings: Res<ChunkStreamingSettings>,
     camera_query: Query<(&Transform, &OrthographicProjection), With<Camera2d>>,
-    chunks_query: Query<(Entity, &ChunkData)>,
+    mut chunks_query: Query<(Entity, &ChunkData, &mut PaintedSamples, Has<StaleChunk>)>,
     mut chunk_manager: ResMut<ChunkManager>,
 ) {
+    let start = Instant::now();
+    let budget = Duration::from_secs_f32(streaming_settings.frame_budget_ms / 1000.0);
+    let margin = IVec2::splat(streaming_settings.despawn_margin.max(1) as i32);
     for (camera_transform, projection) in camera_query.iter() {
-        let chunk_spawn_distance = calculate_ideal_chunk_spawn_distance(&projection.area);
-        let camera_chunk_pos = camera_pos_to_chunk_pos(&camera_transform.translation.xy());
-        for (entity, chunk_data) in chunks_query.iter() {
+        let (min, max) = visible_chunks(camera_transform, projection);
+        let shows_tiles = lod::lod_level(projection).is_none();
+        let out_of_range = |chunk_pos: IVec2| {
+            !shows_tiles
+                || chunk_pos.cmplt(min - margin).any()
+                || chunk_pos.cmpgt(max + margin).any()
+        };
+        chunk_manager.cancel_pending(out_of_range);
+        for (entity, chunk_data, mut painted, stale) in chunks_query.iter_mut() {
             let chunk_pos = chunk_data.position;
-            let distance = IVec2::new(
-                (chunk_pos.x - camera_chunk_pos.x).abs(),
-                (chunk_pos.y - camera_chunk_pos.y).abs(),
-            );
-            if distance.x > chunk_spawn_distance.x + 1 || distance.y > chunk_spawn_distance.y + 1 {
+            if out_of_range(chunk_pos) {
                 chunk_manager.spawned_chunks.remove(&chunk_pos);
                 commands.entity(entity).despawn_recursive();
-                return;
+                if let Some(samples) = painted.0.take().filter(|_| !stale) {
+                    chunk_manager
+                        .cache
+                        .insert(chunk_pos, samples, streaming_settings.cache_size);
+                }
+                if start.elapsed() >= budget {
+                    return;
+                }
             }
         }
     }
@@ -182,45 +646,137 @@ fn regenerate_map_event_listener(
         }
 
         chunk_manager.spawned_chunks.clear();
+        chunk_manager.pending_chunks.clear();
+        chunk_manager.ready_chunks.clear();
+        chunk_manager.cache.clear();
     }
 }
 
-fn highlight_tile_below_cursor(
+// Chunks are repainted a few at a time like for RepaintMapEvent, sampling all of them at once
+// would stall the frame.
+fn repaint_chunks_on_view_mode_change(
     mut commands: Commands,
-    cursor_pos: Res<CursorPos>,
-    tilemap_q: Query<(
-        &TilemapSize,
-        &TilemapGridSize,
-        &TilemapType,
-        &TileStorage,
-        &Transform,
-    )>,
-    highlighted_tiles_q: Query<Entity, With<HighlightedTile>>,
+    view_mode: Res<ViewMode>,
+    mut chunk_manager: ResMut<ChunkManager>,
+    chunks_query: Query<Entity, With<ChunkData>>,
 ) {
-    // Un-highlight any previously highlighted tile labels.
-    for entity in highlighted_tiles_q.iter() {
-        commands.entity(entity).remove::<HighlightedTile>();
-    }
-
-    let cursor_pos: Vec4 = Vec4::from((cursor_pos.world, 0.0, 1.0));
-    for (map_size, grid_size, map_type, tile_storage, map_transform) in tilemap_q.iter() {
-        // We need to make sure that the cursor's world position is correct relative to the map
-        // due to any map transformation.
-        let cursor_pos_relative_to_tilemap: Vec2 = {
-            let cursor_in_map_pos = map_transform.compute_matrix().inverse() * cursor_pos;
-            cursor_in_map_pos.xy()
-        };
-        // Once we have a world position we can transform it into a possible tile position.
-        if let Some(tile_pos) = TilePos::from_world_pos(
-            &cursor_pos_relative_to_tilemap,
-            map_size,
-            grid_size,
-            map_type,
-        ) {
-            // Highlight the relevant tile's label
-            if let Some(tile_entity) = tile_storage.get(&tile_pos) {
-                commands.entity(tile_entity).insert(HighlightedTile);
+    if !view_mode.is_changed() {
+        return;
+    }
+
+    // Pending and cached samples were taken with the padding of the previous view mode.
+    chunk_manager.invalidate(|_| true);
+    for entity in chunks_query.iter() {
+        commands.entity(entity).insert(StaleChunk);
+    }
+}
+
+fn repaint_tiles_event_listener(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut events: EventReader<RepaintTilesEvent>,
+    painter_params: PainterParams,
+    mut chunk_manager: ResMut<ChunkManager>,
+    mut chunks_query: EditableChunkQuery,
+    mut tiles_query: PaintedTileQuery,
+) {
+    let painter = painter_params.painter();
+    for event in events.read() {
+        // Neighbours of changed tiles need to be repainted as well, their autotiles and
+        // shading depend on them.
+        let min = coords::tile_to_chunk_pos(event.min - IVec2::ONE);
+        let max = coords::tile_to_chunk_pos(event.max + IVec2::ONE);
+        chunk_manager
+            .invalidate(|chunk_pos| chunk_pos.cmpge(min).all() && chunk_pos.cmple(max).all());
+        for (entity, chunk_data, tile_storage, mut decorations, mut terrain, mut painted) in
+            chunks_query.iter_mut()
+        {
+            let chunk_pos = chunk_data.position;
+            if chunk_pos.cmplt(min).any() || chunk_pos.cmpgt(max).any() {
+                continue;
             }
+
+            let samples = painter.sample_chunk(chunk_pos);
+            repaint_chunk(
+                &samples,
+                chunk_pos,
+                tile_storage,
+                &painter,
+                &mut tiles_query,
+            );
+            *terrain = ChunkTerrain::new(chunk_pos, &samples.tiles);
+            respawn_decorations(
+                &mut commands,
+                &asset_server,
+                entity,
+                chunk_pos,
+                &samples,
+                &mut decorations,
+            );
+            painted.0 = Some(samples);
         }
     }
 }
+
+fn repaint_map_event_listener(
+    mut commands: Commands,
+    mut events: EventReader<RepaintMapEvent>,
+    mut chunk_manager: ResMut<ChunkManager>,
+    chunks_query: Query<Entity, With<ChunkData>>,
+) {
+    if events.read().count() == 0 {
+        return;
+    }
+    chunk_manager.invalidate(|_| true);
+    for entity in chunks_query.iter() {
+        commands.entity(entity).insert(StaleChunk);
+    }
+}
+
+fn repaint_stale_chunks(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    painter_params: PainterParams,
+    camera_query: Query<&Transform, With<Camera2d>>,
+    mut chunks_query: EditableChunkQuery<With<StaleChunk>>,
+    mut tiles_query: PaintedTileQuery,
+) {
+    if chunks_query.is_empty() {
+        return;
+    }
+    let camera_chunk_pos = camera_query
+        .get_single()
+        .map(|transform| coords::world_to_chunk_pos(transform.translation.xy()))
+        .unwrap_or_default();
+
+    let mut stale_chunks: Vec<_> = chunks_query.iter_mut().collect();
+    stale_chunks.sort_by_key(|(_, chunk_data, _, _, _, _)| {
+        (chunk_data.position - camera_chunk_pos).length_squared()
+    });
+
+    let painter = painter_params.painter();
+    for (entity, chunk_data, tile_storage, mut decorations, mut terrain, mut painted) in
+        stale_chunks.into_iter().take(STALE_CHUNKS_PER_FRAME)
+    {
+        let chunk_pos = chunk_data.position;
+        let samples = painter.sample_chunk(chunk_pos);
+        repaint_chunk(
+            &samples,
+            chunk_pos,
+            tile_storage,
+            &painter,
+            &mut tiles_query,
+        );
+        *terrain = ChunkTerrain::new(chunk_pos, &samples.tiles);
+        respawn_decorations(
+            &mut commands,
+            &asset_server,
+            entity,
+            chunk_pos,
+            &samples,
+            &mut decorations,
+        );
+        painted.0 = Some(samples);
+        commands.entity(entity).remove::<StaleChunk>();
+    }
+}
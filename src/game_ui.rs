@@ -1,20 +1,32 @@
-use crate::game_map::{ChunkData, HighlightedTile, CHUNK_SIZE};
-use crate::noise_generator::{NoiseGenerator, NoiseValues};
-use crate::tile_data::TileData;
+use crate::chunk_terrain::Terrain;
+use crate::coords;
+use crate::game::CursorPos;
+use crate::game_map::{ChunkStreamingSettings, RepaintMapEvent, TileRenderSettings};
+use crate::noise_generator::{
+    NoiseGenerator, NoiseValues, HEIGHT_OFFSET_RANGE, HUMIDITY_OFFSET_RANGE, RESOLUTION_RANGE,
+};
+use crate::preset::{GeneratorPreset, Presets};
+use crate::view_mode::ViewMode;
+use crate::world_code;
 use bevy::prelude::*;
-use bevy_ecs_tilemap::prelude::*;
-use bevy_egui::egui::{Align2, Grid, Pos2, Widget};
+use bevy_egui::egui::{Align2, ComboBox, Grid, Pos2, Widget};
 use bevy_egui::*;
 
 pub struct GameUIPlugin;
 impl Plugin for GameUIPlugin {
     fn build(&self, app: &mut App) {
         app.add_plugins(EguiPlugin)
+            .add_systems(Update, tile_info_ui_system)
             .add_systems(Update, ui_system)
+            .add_systems(Update, live_preview_system.after(ui_system))
+            .add_systems(Update, view_mode_ui_system)
             .add_event::<RegenerateMapEvent>()
+            .init_resource::<Presets>()
             .insert_resource(UnappliedSettings {
                 seed: String::from("42"),
                 map_generator_values: NoiseValues::default(),
+                preset_name: String::from("My Preset"),
+                live_preview: false,
             });
     }
 }
@@ -23,43 +35,77 @@ impl Plugin for GameUIPlugin {
 pub struct UnappliedSettings {
     pub map_generator_values: NoiseValues,
     pub seed: String,
+    // Name used when saving the current settings as a preset.
+    pub preset_name: String,
+    // Applies the settings shortly after they stop changing, repainting the map in place.
+    pub live_preview: bool,
+}
+impl UnappliedSettings {
+    pub fn from_preset(preset: &GeneratorPreset) -> Self {
+        UnappliedSettings {
+            map_generator_values: preset.noise_values,
+            seed: preset.seed.clone(),
+            preset_name: preset.name.clone(),
+            live_preview: false,
+        }
+    }
+
+    fn set_preset(&mut self, preset: &GeneratorPreset) {
+        self.map_generator_values = preset.noise_values;
+        self.seed = preset.seed.clone();
+        self.preset_name = preset.name.clone();
+    }
+
+    fn to_preset(&self) -> GeneratorPreset {
+        GeneratorPreset {
+            name: self.preset_name.clone(),
+            seed: self.seed.clone(),
+            noise_values: self.map_generator_values,
+        }
+    }
 }
 
 #[derive(Event)]
 pub struct RegenerateMapEvent;
 
+#[derive(Default)]
+struct WorldCodeInput {
+    code: String,
+    error: Option<String>,
+}
+
+fn tile_info_ui_system(mut contexts: EguiContexts, cursor_pos: Res<CursorPos>, terrain: Terrain) {
+    let world_tile_pos = coords::world_to_tile_pos(cursor_pos.world);
+    let Some(tile_data) = terrain.tile_data(world_tile_pos) else {
+        return;
+    };
+    let chunk_pos = coords::tile_to_chunk_pos(world_tile_pos);
+    let local_pos = coords::tile_to_local_pos(world_tile_pos);
+    egui::Window::new(format!("{} | {}", world_tile_pos.x, world_tile_pos.y))
+        .collapsible(false)
+        .resizable(false)
+        .fixed_pos(Pos2::new(5.0, 5.0))
+        .show(contexts.ctx_mut(), |ui| {
+            ui.label(tile_data.to_string());
+            ui.separator();
+            ui.heading("Chunk Data");
+            ui.label(format!("Local: x: {} | y: {}", local_pos.x, local_pos.y));
+            ui.label(format!("Chunk: x: {} | y: {}", chunk_pos.x, chunk_pos.y));
+        });
+}
+
 fn ui_system(
     mut contexts: EguiContexts,
-    tile_query: Query<(&TilePos, &TilemapId, &TileData), With<HighlightedTile>>,
-    tilemap_query: Query<(Entity, &ChunkData)>,
     mut map_gen: ResMut<NoiseGenerator>,
     mut unapplied_settings: ResMut<UnappliedSettings>,
+    mut render_settings: ResMut<TileRenderSettings>,
+    mut presets: ResMut<Presets>,
     mut regenerate_map_event: EventWriter<RegenerateMapEvent>,
+    mut repaint_map_event: EventWriter<RepaintMapEvent>,
+    mut world_code_input: Local<WorldCodeInput>,
 ) {
-    if let Ok((tile_pos, tilemap_id, tile_data)) = tile_query.get_single() {
-        if let Ok((_, chunk_data)) = tilemap_query.get(tilemap_id.0) {
-            egui::Window::new(format!(
-                "{} | {}",
-                tile_pos.x as i32 + chunk_data.position.x * CHUNK_SIZE.x as i32,
-                tile_pos.y as i32 + chunk_data.position.y * CHUNK_SIZE.y as i32
-            ))
-            .collapsible(false)
-            .resizable(false)
-            .fixed_pos(Pos2::new(5.0, 5.0))
-            .show(contexts.ctx_mut(), |ui| {
-                ui.label(tile_data.to_string());
-                ui.separator();
-                ui.heading("Chunk Data");
-                ui.label(format!("Local: x: {} | y: {}", tile_pos.x, tile_pos.y));
-                ui.label(format!(
-                    "Chunk: x: {} | y: {}",
-                    chunk_data.position.x, chunk_data.position.y
-                ));
-            });
-        }
-    }
-
     let mut apply = false;
+    let mut rerender = false;
     egui::Window::new("Settings")
         .collapsible(false)
         .resizable(false)
@@ -67,6 +113,23 @@ fn ui_system(
         .fixed_pos(Pos2::new(5.0, 5.0))
         .show(contexts.ctx_mut(), |ui| {
             Grid::new("table").show(ui, |ui| {
+                ui.label("Preset:");
+                let mut selected_preset = None;
+                ComboBox::from_id_source("preset")
+                    .selected_text(&unapplied_settings.preset_name)
+                    .show_ui(ui, |ui| {
+                        for preset in &presets.presets {
+                            if ui.selectable_label(false, &preset.name).clicked() {
+                                selected_preset = Some(preset);
+                            }
+                        }
+                    });
+                if let Some(preset) = selected_preset {
+                    unapplied_settings.set_preset(preset);
+                    apply = true;
+                }
+                ui.end_row();
+
                 ui.label("Seed:");
                 ui.text_edit_singleline(&mut unapplied_settings.seed);
                 ui.end_row();
@@ -74,13 +137,109 @@ fn ui_system(
                 ui.label("Biome Resolution:");
                 egui::Slider::new(
                     &mut unapplied_settings.map_generator_values.resolution,
-                    0.001..=0.1,
+                    RESOLUTION_RANGE,
+                )
+                .ui(ui);
+                ui.end_row();
+
+                ui.label("Height Offset:");
+                egui::Slider::new(
+                    &mut unapplied_settings.map_generator_values.height_offset,
+                    HEIGHT_OFFSET_RANGE,
                 )
                 .ui(ui);
                 ui.end_row();
+
+                ui.label("Humidity Offset:");
+                egui::Slider::new(
+                    &mut unapplied_settings.map_generator_values.humidity_offset,
+                    HUMIDITY_OFFSET_RANGE,
+                )
+                .ui(ui);
+                ui.end_row();
+
+                ui.label("Tile Variants:");
+                rerender |= ui.checkbox(&mut render_settings.variants, "").changed();
+                ui.end_row();
+
+                ui.label("Tint Tiles:");
+                rerender |= ui.checkbox(&mut render_settings.tint, "").changed();
+                ui.end_row();
+
+                ui.label("Shading:");
+                rerender |= ui.checkbox(&mut render_settings.shading, "").changed();
+                ui.end_row();
+
+                if render_settings.shading {
+                    ui.label("Light Direction:");
+                    let slider = egui::Slider::new(&mut render_settings.light_azimuth, 0.0..=360.0);
+                    rerender |= slider_committed(&slider.ui(ui));
+                    ui.end_row();
+
+                    ui.label("Light Elevation:");
+                    let slider =
+                        egui::Slider::new(&mut render_settings.light_elevation, 10.0..=90.0);
+                    rerender |= slider_committed(&slider.ui(ui));
+                    ui.end_row();
+
+                    ui.label("Relief:");
+                    let slider = egui::Slider::new(&mut render_settings.relief, 1.0..=100.0);
+                    rerender |= slider_committed(&slider.ui(ui));
+                    ui.end_row();
+                }
             });
 
-            apply = ui.button("Apply").clicked();
+            ui.horizontal(|ui| {
+                apply |= ui.button("Apply").clicked();
+                ui.checkbox(&mut unapplied_settings.live_preview, "Live Preview");
+
+                // There is no file system to save to in the browser.
+                #[cfg(not(target_arch = "wasm32"))]
+                {
+                    ui.separator();
+                    ui.text_edit_singleline(&mut unapplied_settings.preset_name);
+                    if ui.button("Save Preset").clicked() {
+                        let preset = unapplied_settings.to_preset();
+                        let path = preset.user_preset_path();
+                        match preset.save(&path) {
+                            Ok(()) => {
+                                info!("Saved preset to {}", path.display());
+                                presets.insert(preset);
+                            }
+                            Err(error) => error!("Failed to save preset: {error}"),
+                        }
+                    }
+                }
+            });
+
+            ui.separator();
+            ui.horizontal(|ui| {
+                ui.label("World Code:");
+                ui.text_edit_singleline(&mut world_code_input.code);
+                if ui.button("Copy").clicked() {
+                    let code = world_code::encode(
+                        &unapplied_settings.seed,
+                        &unapplied_settings.map_generator_values,
+                    );
+                    // In the browser a link is more useful than the bare code.
+                    #[cfg(target_arch = "wasm32")]
+                    let code = world_code::share_url(&code).unwrap_or(code);
+                    ui.output_mut(|output| output.copied_text = code);
+                }
+                if ui.button("Load").clicked() {
+                    match world_code::decode(&world_code_input.code) {
+                        Ok(preset) => {
+                            unapplied_settings.set_preset(&preset);
+                            world_code_input.error = None;
+                            apply = true;
+                        }
+                        Err(error) => world_code_input.error = Some(error.to_string()),
+                    }
+                }
+            });
+            if let Some(error) = &world_code_input.error {
+                ui.colored_label(egui::Color32::RED, error);
+            }
         });
 
     if apply {
@@ -88,7 +247,98 @@ fn ui_system(
             &unapplied_settings.seed,
             unapplied_settings.map_generator_values,
         );
+        #[cfg(target_arch = "wasm32")]
+        world_code::update_url(&world_code::encode(
+            &unapplied_settings.seed,
+            &unapplied_settings.map_generator_values,
+        ));
         map_gen.set_if_neq(new_map);
         regenerate_map_event.send(RegenerateMapEvent);
+    } else if rerender {
+        // Only how the tiles look changed, so the chunks can be repainted where they are.
+        repaint_map_event.send(RepaintMapEvent);
     }
 }
+
+// Seconds the settings have to stay the same before the live preview applies them. The settings
+// history waits as long before recording a previewed world.
+pub const LIVE_PREVIEW_DELAY: f32 = 0.25;
+
+#[derive(Default)]
+struct PendingPreview {
+    seed: String,
+    values: Option<NoiseValues>,
+    changed_at: f32,
+    applied: bool,
+}
+
+fn live_preview_system(
+    time: Res<Time>,
+    unapplied_settings: Res<UnappliedSettings>,
+    mut map_gen: ResMut<NoiseGenerator>,
+    mut repaint_map_event: EventWriter<RepaintMapEvent>,
+    mut pending: Local<PendingPreview>,
+) {
+    if !unapplied_settings.live_preview {
+        pending.values = None;
+        return;
+    }
+
+    // The sliders borrow the settings mutably every frame, so change detection can't be used.
+    let values = unapplied_settings.map_generator_values;
+    if pending.values != Some(values) || pending.seed != unapplied_settings.seed {
+        pending.seed = unapplied_settings.seed.clone();
+        pending.values = Some(values);
+        pending.changed_at = time.elapsed_seconds();
+        pending.applied = false;
+        return;
+    }
+    if pending.applied || time.elapsed_seconds() - pending.changed_at < LIVE_PREVIEW_DELAY {
+        return;
+    }
+
+    pending.applied = true;
+    if map_gen.set_if_neq(NoiseGenerator::new(&pending.seed, values)) {
+        #[cfg(target_arch = "wasm32")]
+        world_code::update_url(&world_code::encode(&pending.seed, &values));
+        repaint_map_event.send(RepaintMapEvent);
+    }
+}
+
+fn view_mode_ui_system(
+    mut contexts: EguiContexts,
+    mut view_mode: ResMut<ViewMode>,
+    mut streaming_settings: ResMut<ChunkStreamingSettings>,
+) {
+    let mut selected = *view_mode;
+    egui::Window::new("View")
+        .collapsible(true)
+        .resizable(false)
+        .anchor(Align2::RIGHT_TOP, egui::Vec2::new(-5.0, 5.0))
+        .show(contexts.ctx_mut(), |ui| {
+            for mode in ViewMode::ALL {
+                ui.radio_value(&mut selected, mode, mode.name());
+            }
+            if !selected.legend().is_empty() {
+                ui.separator();
+                ui.label(selected.legend());
+            }
+            ui.separator();
+            egui::Slider::new(&mut streaming_settings.frame_budget_ms, 0.5..=16.0)
+                .text("Chunk Budget (ms)")
+                .ui(ui);
+            egui::Slider::new(&mut streaming_settings.despawn_margin, 1..=8)
+                .text("Despawn Margin")
+                .ui(ui);
+            egui::Slider::new(&mut streaming_settings.cache_size, 0..=256)
+                .text("Chunk Cache")
+                .ui(ui);
+        });
+
+    view_mode.set_if_neq(selected);
+}
+
+// Regenerating while a slider is still being dragged would restart chunk spawning every frame.
+fn slider_committed(response: &egui::Response) -> bool {
+    response.drag_released() || (response.changed() && !response.dragged())
+}
@@ -0,0 +1,28 @@
+pub mod autotile;
+pub mod batch_noise;
+pub mod biome;
+pub mod camera;
+pub mod chunk_cache;
+pub mod chunk_terrain;
+pub mod cli;
+pub mod coords;
+pub mod data_export;
+pub mod editor;
+pub mod export;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod export_ui;
+pub mod game;
+pub mod game_map;
+pub mod game_ui;
+pub mod lod;
+pub mod minimap;
+pub mod noise_generator;
+pub mod preset;
+pub mod settings_history;
+pub mod tile_data;
+pub mod tile_deltas;
+pub mod tiled;
+pub mod view_mode;
+pub mod world_code;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod world_save;
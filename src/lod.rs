@@ -0,0 +1,310 @@
+use crate::camera;
+use crate::coords;
+use crate::game_map::{RepaintMapEvent, RepaintTilesEvent, TILE_SIZE};
+use crate::game_ui::RegenerateMapEvent;
+use crate::noise_generator::NoiseGenerator;
+use crate::tile_data::TerrainGrid;
+use crate::tile_deltas::TileDeltas;
+use bevy::prelude::*;
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+use bevy::tasks::{block_on, futures_lite::future, AsyncComputeTaskPool, Task};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+// Below this many screen pixels per tile the map is drawn as one texture per LOD chunk instead of
+// tile entities.
+const MIN_PIXELS_PER_TILE: f32 = 4.0;
+// Width and height of every LOD chunk texture. Each level doubles the tiles per pixel, so LOD
+// chunks always end up between one and two times this size on screen.
+const LOD_IMAGE_SIZE: u32 = 64;
+// Below the tiles, finer levels on top of coarser ones, so the previous level stays visible
+// until the next one is complete.
+const LOD_Z: f32 = -100.0;
+const MAX_PENDING_LOD_CHUNKS: usize = 16;
+
+pub struct LodPlugin;
+impl Plugin for LodPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LodManager>().add_systems(
+            Update,
+            (
+                clear_lod_chunks_on_map_change,
+                spawn_lod_chunks,
+                spawn_generated_lod_chunks,
+                despawn_lod_chunks,
+            )
+                .chain(),
+        );
+    }
+}
+
+// None while zoomed in far enough to show tiles, otherwise the LOD level, each level doubling the
+// tiles per texture pixel.
+pub fn lod_level(projection: &OrthographicProjection) -> Option<u32> {
+    let pixels_per_tile = camera::zoom(projection) * TILE_SIZE.x;
+    if pixels_per_tile >= MIN_PIXELS_PER_TILE {
+        return None;
+    }
+    Some((MIN_PIXELS_PER_TILE / pixels_per_tile).log2().floor() as u32)
+}
+
+#[derive(Component)]
+struct LodChunk {
+    level: u32,
+    position: IVec2,
+}
+
+#[derive(Resource, Default)]
+struct LodManager {
+    spawned_chunks: HashSet<(u32, IVec2)>,
+    // Texture data still being rendered on the async compute pool.
+    pending_chunks: HashMap<(u32, IVec2), Task<Vec<u8>>>,
+}
+
+fn tiles_per_pixel(level: u32) -> i32 {
+    1 << level
+}
+
+fn lod_chunk_size_in_tiles(level: u32) -> i32 {
+    LOD_IMAGE_SIZE as i32 * tiles_per_pixel(level)
+}
+
+fn lod_chunk_origin(level: u32, lod_chunk_pos: IVec2) -> IVec2 {
+    lod_chunk_pos * lod_chunk_size_in_tiles(level)
+}
+
+// Inclusive range of LOD chunks of the given level overlapping a rectangle in world space.
+fn lod_chunks_overlapping(level: u32, world_rect: Rect) -> (IVec2, IVec2) {
+    let (min, max) = coords::tiles_overlapping(world_rect);
+    let size = IVec2::splat(lod_chunk_size_in_tiles(level));
+    (min.div_euclid(size), max.div_euclid(size))
+}
+
+fn camera_rect(transform: &Transform, projection: &OrthographicProjection) -> Rect {
+    let camera_pos = transform.translation.xy();
+    Rect {
+        min: camera_pos + projection.area.min,
+        max: camera_pos + projection.area.max,
+    }
+}
+
+// One pixel per sampled tile, top row first. Uses the same colors as the minimap.
+fn render_lod_chunk(
+    noise: &NoiseGenerator,
+    deltas: &TileDeltas,
+    level: u32,
+    lod_chunk_pos: IVec2,
+) -> Vec<u8> {
+    let origin = lod_chunk_origin(level, lod_chunk_pos);
+    let step = tiles_per_pixel(level);
+    // Sampled in the middle of the tiles covered by each pixel, indexed by pixel.
+    let first_tile = origin + IVec2::splat(step / 2);
+    let size = UVec2::splat(LOD_IMAGE_SIZE);
+    let (height, humidity) = noise.sample_layers(first_tile, size, step);
+    let pixels = TerrainGrid::new(IVec2::ZERO, size, height, humidity);
+    let mut data = Vec::with_capacity((LOD_IMAGE_SIZE * LOD_IMAGE_SIZE * 4) as usize);
+    for y in (0..LOD_IMAGE_SIZE as i32).rev() {
+        for x in 0..LOD_IMAGE_SIZE as i32 {
+            let pixel = IVec2::new(x, y);
+            let mut tile_data = pixels.get(pixel);
+            deltas.get(first_tile + pixel * step).apply(&mut tile_data);
+            let color = tile_data.get_tile_type().get_map_color();
+            data.extend_from_slice(&color.as_rgba_u8());
+        }
+    }
+    data
+}
+
+fn clear_lod_chunks_on_map_change(
+    mut commands: Commands,
+    mut regenerate_map_event: EventReader<RegenerateMapEvent>,
+    mut repaint_map_event: EventReader<RepaintMapEvent>,
+    mut repaint_tiles_event: EventReader<RepaintTilesEvent>,
+    mut lod_manager: ResMut<LodManager>,
+    lod_chunks_query: Query<(Entity, &LodChunk)>,
+) {
+    let map_changed =
+        regenerate_map_event.read().count() > 0 || repaint_map_event.read().count() > 0;
+    let edited: Vec<(IVec2, IVec2)> = repaint_tiles_event
+        .read()
+        .map(|event| (event.min, event.max))
+        .collect();
+    if !map_changed && edited.is_empty() {
+        return;
+    }
+
+    let outdated = |level: u32, lod_chunk_pos: IVec2| {
+        let min = lod_chunk_origin(level, lod_chunk_pos);
+        let max = min + IVec2::splat(lod_chunk_size_in_tiles(level) - 1);
+        map_changed
+            || edited
+                .iter()
+                .any(|(edit_min, edit_max)| edit_min.cmple(max).all() && edit_max.cmpge(min).all())
+    };
+    for (entity, lod_chunk) in lod_chunks_query.iter() {
+        if outdated(lod_chunk.level, lod_chunk.position) {
+            commands.entity(entity).despawn_recursive();
+            lod_manager
+                .spawned_chunks
+                .remove(&(lod_chunk.level, lod_chunk.position));
+        }
+    }
+    let LodManager {
+        spawned_chunks,
+        pending_chunks,
+    } = &mut *lod_manager;
+    pending_chunks.retain(|key, _| {
+        let keep = !outdated(key.0, key.1);
+        if !keep {
+            spawned_chunks.remove(key);
+        }
+        keep
+    });
+}
+
+fn spawn_lod_chunks(
+    camera_query: Query<(&Transform, &OrthographicProjection), With<Camera2d>>,
+    mut lod_manager: ResMut<LodManager>,
+    noise: Res<NoiseGenerator>,
+    deltas: Res<TileDeltas>,
+) {
+    let Ok((transform, projection)) = camera_query.get_single() else {
+        return;
+    };
+    let Some(level) = lod_level(projection) else {
+        return;
+    };
+
+    let (min, max) = lod_chunks_overlapping(level, camera_rect(transform, projection));
+    let mut missing_chunks: Vec<IVec2> = (min.y..=max.y)
+        .flat_map(|y| (min.x..=max.x).map(move |x| IVec2::new(x, y)))
+        .filter(|lod_chunk_pos| {
+            !lod_manager
+                .spawned_chunks
+                .contains(&(level, *lod_chunk_pos))
+        })
+        .collect();
+    let center = (min + max).as_vec2() * 0.5;
+    missing_chunks.sort_by(|a, b| {
+        let distance = |pos: &IVec2| pos.as_vec2().distance_squared(center);
+        distance(a).total_cmp(&distance(b))
+    });
+
+    let task_pool = AsyncComputeTaskPool::get();
+    // Shared by all LOD chunks started this frame instead of cloning the generator for each.
+    let mut shared_noise: Option<Arc<NoiseGenerator>> = None;
+    for lod_chunk_pos in missing_chunks {
+        if lod_manager.pending_chunks.len() >= MAX_PENDING_LOD_CHUNKS {
+            return;
+        }
+        lod_manager.spawned_chunks.insert((level, lod_chunk_pos));
+
+        let noise = shared_noise
+            .get_or_insert_with(|| Arc::new(NoiseGenerator::clone(&noise)))
+            .clone();
+        let deltas = deltas.region(
+            lod_chunk_origin(level, lod_chunk_pos),
+            UVec2::splat(lod_chunk_size_in_tiles(level) as u32),
+        );
+        let task =
+            task_pool.spawn(async move { render_lod_chunk(&noise, &deltas, level, lod_chunk_pos) });
+        lod_manager
+            .pending_chunks
+            .insert((level, lod_chunk_pos), task);
+    }
+}
+
+fn spawn_generated_lod_chunks(
+    mut commands: Commands,
+    mut images: ResMut<Assets<Image>>,
+    mut lod_manager: ResMut<LodManager>,
+) {
+    lod_manager
+        .pending_chunks
+        .retain(|(level, lod_chunk_pos), task| {
+            let Some(data) = block_on(future::poll_once(task)) else {
+                return true;
+            };
+
+            let image = Image::new(
+                Extent3d {
+                    width: LOD_IMAGE_SIZE,
+                    height: LOD_IMAGE_SIZE,
+                    depth_or_array_layers: 1,
+                },
+                TextureDimension::D2,
+                data,
+                TextureFormat::Rgba8UnormSrgb,
+            );
+            // Tiles are centered on their position, so the covered area starts half a tile earlier.
+            let size_in_tiles = lod_chunk_size_in_tiles(*level);
+            let size = Vec2::splat(size_in_tiles as f32) * coords::tile_size();
+            let center = coords::tile_to_world_pos(lod_chunk_origin(*level, *lod_chunk_pos))
+                - coords::tile_size() * 0.5
+                + size * 0.5;
+            commands.spawn((
+                SpriteBundle {
+                    texture: images.add(image),
+                    sprite: Sprite {
+                        custom_size: Some(size),
+                        ..default()
+                    },
+                    transform: Transform::from_translation(center.extend(LOD_Z - *level as f32)),
+                    ..default()
+                },
+                LodChunk {
+                    level: *level,
+                    position: *lod_chunk_pos,
+                },
+            ));
+            false
+        });
+}
+
+// LOD chunks of other levels are kept until the current level is complete, so switching levels
+// never shows gaps. While tiles are shown they stay below them until out of range, covering
+// chunks that haven't been spawned yet.
+fn despawn_lod_chunks(
+    mut commands: Commands,
+    camera_query: Query<(&Transform, &OrthographicProjection), With<Camera2d>>,
+    mut lod_manager: ResMut<LodManager>,
+    lod_chunks_query: Query<(Entity, &LodChunk)>,
+) {
+    let Ok((transform, projection)) = camera_query.get_single() else {
+        return;
+    };
+    let level = lod_level(projection);
+    let rect = camera_rect(transform, projection);
+    let out_of_range = |lod_level: u32, lod_chunk_pos: IVec2| {
+        let (min, max) = lod_chunks_overlapping(lod_level, rect);
+        lod_chunk_pos.cmplt(min - IVec2::ONE).any() || lod_chunk_pos.cmpgt(max + IVec2::ONE).any()
+    };
+
+    let LodManager {
+        spawned_chunks,
+        pending_chunks,
+    } = &mut *lod_manager;
+    pending_chunks.retain(|key, _| {
+        let keep = Some(key.0) == level && !out_of_range(key.0, key.1);
+        if !keep {
+            spawned_chunks.remove(key);
+        }
+        keep
+    });
+
+    // Coarser and finer levels stay until every chunk of the current level in view is rendered,
+    // so zooming never shows gaps. Pending chunks of the current level count as missing.
+    let level_complete = level.is_some_and(|level| {
+        let (min, max) = lod_chunks_overlapping(level, rect);
+        (min.y..=max.y)
+            .flat_map(|y| (min.x..=max.x).map(move |x| (level, IVec2::new(x, y))))
+            .all(|key| spawned_chunks.contains(&key) && !pending_chunks.contains_key(&key))
+    });
+    for (entity, lod_chunk) in lod_chunks_query.iter() {
+        let replaced = level.is_some_and(|level| level != lod_chunk.level) && level_complete;
+        if replaced || out_of_range(lod_chunk.level, lod_chunk.position) {
+            commands.entity(entity).despawn_recursive();
+            spawned_chunks.remove(&(lod_chunk.level, lod_chunk.position));
+        }
+    }
+}
@@ -1,21 +1,32 @@
-mod biome;
-mod camera;
-mod game;
-mod game_map;
-mod game_ui;
-mod noise_generator;
-mod tile_data;
-
 use bevy::asset::AssetMetaCheck;
 use bevy::prelude::*;
 use bevy::window::PresentMode;
 use bevy_screen_diagnostics::{
     ScreenDiagnosticsPlugin, ScreenEntityDiagnosticsPlugin, ScreenFrameDiagnosticsPlugin,
 };
+use clap::Parser;
+use procjam_2023::preset::GeneratorPreset;
+use procjam_2023::{cli, game, game_ui};
 
 fn main() {
-    App::new()
-        .insert_resource(AssetMetaCheck::Never)
+    let cli = cli::Cli::parse();
+    if let Some(command) = cli.command {
+        if let Err(error) = cli::run(command) {
+            eprintln!("{error}");
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    let preset = cli.startup_preset();
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Err(error) = &preset {
+        eprintln!("{error}");
+        std::process::exit(1);
+    }
+
+    let mut app = App::new();
+    app.insert_resource(AssetMetaCheck::Never)
         .add_plugins(
             DefaultPlugins
                 .set(ImagePlugin::default_nearest())
@@ -30,6 +41,17 @@ fn main() {
         .add_plugins(game::GamePlugin)
         .add_plugins(ScreenDiagnosticsPlugin::default())
         .add_plugins(ScreenFrameDiagnosticsPlugin)
-        .add_plugins(ScreenEntityDiagnosticsPlugin)
-        .run();
+        .add_plugins(ScreenEntityDiagnosticsPlugin);
+
+    // Only the browser gets here with an error. A broken link should still open a world, so the
+    // error goes to the console, which works once the log plugin has been added.
+    let preset = preset.unwrap_or_else(|error| {
+        warn!("{error}, starting with the default preset");
+        Some(GeneratorPreset::default())
+    });
+    if let Some(preset) = preset {
+        app.insert_resource(game_ui::UnappliedSettings::from_preset(&preset))
+            .insert_resource(preset.noise_generator());
+    }
+    app.run();
 }
@@ -0,0 +1,241 @@
+use crate::coords;
+use crate::noise_generator::NoiseGenerator;
+use crate::tile_data::TerrainGrid;
+use crate::tile_deltas::TileDeltas;
+use bevy::prelude::*;
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+use bevy_egui::egui::{Align2, Color32, Sense, Stroke};
+use bevy_egui::*;
+
+// The minimaps are sampled straight from the noise generator with the tile edits on top, so they
+// can show a lot more than what has been spawned as chunks. They are only redrawn once the camera
+// moved this many pixels, or when the generator or the edits change.
+const REDRAW_DISTANCE_IN_PIXELS: u32 = 4;
+
+pub struct MinimapPlugin;
+impl Plugin for MinimapPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, init)
+            .add_systems(Update, render_minimaps)
+            .add_systems(Update, minimap_ui_system);
+    }
+}
+
+#[derive(Resource)]
+struct Minimap {
+    overview: bool,
+    corner: MinimapImage,
+    full_screen: MinimapImage,
+}
+
+struct MinimapImage {
+    handle: Handle<Image>,
+    size: u32,
+    tiles_per_pixel: u32,
+    display_scale: f32,
+    // Tile in the middle of the image the last time it was rendered.
+    center: Option<IVec2>,
+}
+
+impl MinimapImage {
+    fn new(
+        images: &mut Assets<Image>,
+        size: u32,
+        tiles_per_pixel: u32,
+        display_scale: f32,
+    ) -> Self {
+        let image = Image::new(
+            Extent3d {
+                width: size,
+                height: size,
+                depth_or_array_layers: 1,
+            },
+            TextureDimension::D2,
+            vec![0; (size * size * 4) as usize],
+            TextureFormat::Rgba8UnormSrgb,
+        );
+
+        MinimapImage {
+            handle: images.add(image),
+            size,
+            tiles_per_pixel,
+            display_scale,
+            center: None,
+        }
+    }
+
+    fn extent_in_tiles(&self) -> f32 {
+        (self.size * self.tiles_per_pixel) as f32
+    }
+
+    fn origin(&self) -> IVec2 {
+        let half_extent = (self.size * self.tiles_per_pixel / 2) as i32;
+        self.center.unwrap_or_default() - IVec2::splat(half_extent)
+    }
+
+    fn update(
+        &mut self,
+        camera_tile: IVec2,
+        noise: &NoiseGenerator,
+        deltas: &TileDeltas,
+        images: &mut Assets<Image>,
+    ) {
+        let redraw_distance = (self.tiles_per_pixel * REDRAW_DISTANCE_IN_PIXELS) as i32;
+        if let Some(center) = self.center {
+            if (center - camera_tile).abs().max_element() < redraw_distance {
+                return;
+            }
+        }
+
+        self.center = Some(camera_tile);
+        let origin = self.origin();
+        let Some(image) = images.get_mut(&self.handle) else {
+            return;
+        };
+
+        // Sampled in the middle of the tiles covered by each pixel, indexed by pixel.
+        let step = self.tiles_per_pixel as i32;
+        let first_tile = origin + IVec2::splat(step / 2);
+        let size = UVec2::splat(self.size);
+        let (height, humidity) = noise.sample_layers(first_tile, size, step);
+        let pixels = TerrainGrid::new(IVec2::ZERO, size, height, humidity);
+        let mut data = Vec::with_capacity(image.data.len());
+        for y in (0..self.size as i32).rev() {
+            for x in 0..self.size as i32 {
+                let pixel = IVec2::new(x, y);
+                let mut tile_data = pixels.get(pixel);
+                deltas.get(first_tile + pixel * step).apply(&mut tile_data);
+                let color = tile_data.get_tile_type().get_map_color();
+                data.extend_from_slice(&color.as_rgba_u8());
+            }
+        }
+        image.data = data;
+    }
+
+    // Image space goes from (0, 0) in the top left to (1, 1) in the bottom right.
+    fn world_to_image(&self, world_pos: Vec2) -> Vec2 {
+        let tile_pos = coords::world_to_tile_space(world_pos) - self.origin().as_vec2();
+        let fraction = tile_pos / self.extent_in_tiles();
+        Vec2::new(fraction.x, 1.0 - fraction.y)
+    }
+
+    fn image_to_world(&self, image_pos: Vec2) -> Vec2 {
+        let fraction = Vec2::new(image_pos.x, 1.0 - image_pos.y);
+        let tile_pos = self.origin().as_vec2() + fraction * self.extent_in_tiles();
+        coords::tile_space_to_world(tile_pos)
+    }
+
+    // Returns the world position that was clicked, if any.
+    fn show(&self, ui: &mut egui::Ui, texture: egui::TextureId, camera_rect: Rect) -> Option<Vec2> {
+        let display_size = egui::Vec2::splat(self.size as f32 * self.display_scale);
+        let response =
+            ui.add(egui::Image::from_texture((texture, display_size)).sense(Sense::click()));
+        let image_rect = response.rect;
+        let to_ui = |world_pos: Vec2| {
+            let image_pos = self.world_to_image(world_pos);
+            image_rect.min + egui::Vec2::new(image_pos.x, image_pos.y) * image_rect.size()
+        };
+
+        ui.painter_at(image_rect).rect_stroke(
+            egui::Rect::from_two_pos(to_ui(camera_rect.min), to_ui(camera_rect.max)),
+            0.0,
+            Stroke::new(1.0, Color32::WHITE),
+        );
+
+        if !response.clicked() {
+            return None;
+        }
+        let click_pos = response.interact_pointer_pos()?;
+        let image_pos = (click_pos - image_rect.min) / image_rect.size();
+        Some(self.image_to_world(Vec2::new(image_pos.x, image_pos.y)))
+    }
+}
+
+fn init(mut commands: Commands, mut images: ResMut<Assets<Image>>) {
+    commands.insert_resource(Minimap {
+        overview: false,
+        corner: MinimapImage::new(&mut images, 128, 4, 1.5),
+        full_screen: MinimapImage::new(&mut images, 384, 32, 1.5),
+    });
+}
+
+fn render_minimaps(
+    mut minimap: ResMut<Minimap>,
+    mut images: ResMut<Assets<Image>>,
+    noise: Res<NoiseGenerator>,
+    deltas: Res<TileDeltas>,
+    camera_query: Query<&Transform, With<Camera2d>>,
+) {
+    let Ok(camera_transform) = camera_query.get_single() else {
+        return;
+    };
+
+    if noise.is_changed() || deltas.is_changed() {
+        minimap.corner.center = None;
+        minimap.full_screen.center = None;
+    }
+
+    let camera_tile = coords::world_to_tile_pos(camera_transform.translation.xy());
+    minimap
+        .corner
+        .update(camera_tile, &noise, &deltas, &mut images);
+    if minimap.overview {
+        minimap
+            .full_screen
+            .update(camera_tile, &noise, &deltas, &mut images);
+    }
+}
+
+fn minimap_ui_system(
+    mut contexts: EguiContexts,
+    mut minimap: ResMut<Minimap>,
+    mut camera_query: Query<(&mut Transform, &OrthographicProjection), With<Camera2d>>,
+) {
+    let Ok((mut camera_transform, projection)) = camera_query.get_single_mut() else {
+        return;
+    };
+    let camera_pos = camera_transform.translation.xy();
+    let camera_rect = Rect::from_corners(
+        camera_pos + projection.area.min,
+        camera_pos + projection.area.max,
+    );
+
+    let corner_texture = contexts.add_image(minimap.corner.handle.clone_weak());
+    let full_screen_texture = contexts.add_image(minimap.full_screen.handle.clone_weak());
+    let ctx = contexts.ctx_mut();
+
+    let mut jump_to = None;
+    let mut toggle_overview = false;
+    egui::Window::new("Minimap")
+        .collapsible(true)
+        .resizable(false)
+        .anchor(Align2::RIGHT_BOTTOM, egui::Vec2::new(-5.0, -5.0))
+        .show(ctx, |ui| {
+            jump_to = minimap.corner.show(ui, corner_texture, camera_rect);
+            toggle_overview = ui.button("Overview").clicked();
+        });
+
+    if minimap.overview {
+        let mut open = true;
+        egui::Window::new("Overview")
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .anchor(Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .show(ctx, |ui| {
+                let clicked = minimap
+                    .full_screen
+                    .show(ui, full_screen_texture, camera_rect);
+                jump_to = jump_to.or(clicked);
+            });
+        minimap.overview &= open;
+    }
+
+    if toggle_overview {
+        minimap.overview = !minimap.overview;
+    }
+
+    if let Some(world_pos) = jump_to {
+        camera_transform.translation = world_pos.extend(camera_transform.translation.z);
+    }
+}
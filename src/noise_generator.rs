@@ -1,28 +1,52 @@
-use crate::tile_data::TileData;
+use crate::batch_noise::{BatchBasicMulti, BatchOpenSimplex, Lanes, LANES};
+use crate::tile_data::{TerrainGrid, TileData};
 use bevy::prelude::*;
-use noise::{MultiFractal, NoiseFn, Seedable};
+use serde::{Deserialize, Serialize};
+use std::ops::RangeInclusive;
 
-#[derive(Resource)]
+// Resolutions the settings allow. Anything far outside of it turns into noise or a single biome.
+pub const RESOLUTION_RANGE: RangeInclusive<f64> = 0.001..=0.1;
+// Offsets the settings allow. Further out the world is only water, land or a single biome.
+pub const HEIGHT_OFFSET_RANGE: RangeInclusive<f32> = -0.5..=0.5;
+pub const HUMIDITY_OFFSET_RANGE: RangeInclusive<f32> = -1.0..=1.0;
+const HUMIDITY_RESOLUTION: f64 = 0.022;
+const BIOME_FREQUENCY: f64 = 5.0;
+const FNV_OFFSET_BASIS: u32 = 0x811c_9dc5;
+const FNV_PRIME: u32 = 0x0100_0193;
+
+#[derive(Resource, Clone)]
 pub struct NoiseGenerator {
     pub values: NoiseValues,
+    // The seed as entered, before hashing it into the one used by the noise functions.
+    seed_text: String,
+    seed: u32,
 
-    height: noise::OpenSimplex,
-    biome: noise::BasicMulti<noise::OpenSimplex>,
+    height: BatchOpenSimplex,
+    biome: BatchBasicMulti,
 }
 
 impl PartialEq for NoiseGenerator {
     fn eq(&self, other: &Self) -> bool {
-        self.values == other.values && self.height.seed() == other.height.seed()
+        self.values == other.values && self.seed == other.seed
     }
 }
 
-#[derive(Copy, Clone, PartialEq)]
+#[derive(Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct NoiseValues {
     pub resolution: f64,
+    // Added to the raw noise, so positive values raise the land and negative ones flood it.
+    pub height_offset: f32,
+    // Added to the raw noise, positive values make the world drier.
+    pub humidity_offset: f32,
 }
 impl Default for NoiseValues {
     fn default() -> Self {
-        NoiseValues { resolution: 0.035 }
+        NoiseValues {
+            resolution: 0.035,
+            height_offset: 0.0,
+            humidity_offset: 0.0,
+        }
     }
 }
 
@@ -34,27 +58,172 @@ impl Default for NoiseGenerator {
 
 impl NoiseGenerator {
     pub fn new(seed: &String, values: NoiseValues) -> Self {
-        let seed = seed.reflect_hash().unwrap_or(42) as u32;
+        let seed_text = seed.clone();
+        let seed = hash_seed(seed);
         NoiseGenerator {
             values,
-            height: noise::OpenSimplex::new(seed),
-            biome: noise::BasicMulti::new(seed).set_frequency(5.0),
+            seed_text,
+            seed,
+            height: BatchOpenSimplex::new(seed),
+            biome: BatchBasicMulti::new(seed, BIOME_FREQUENCY),
         }
     }
 
-    pub fn get_tile_data(&self, chunk_pos: IVec2, tile_pos_x: u32, tile_pos_y: u32) -> TileData {
-        let x = (chunk_pos.x as f64 * crate::game_map::CHUNK_SIZE.x as f64) + tile_pos_x as f64;
-        let y = (chunk_pos.y as f64 * crate::game_map::CHUNK_SIZE.y as f64) + tile_pos_y as f64;
+    pub fn seed(&self) -> u32 {
+        self.seed
+    }
+
+    pub fn seed_text(&self) -> &str {
+        &self.seed_text
+    }
 
+    pub fn get_tile_data(&self, world_tile_pos: IVec2) -> TileData {
+        // The other lanes are wasted, grids should go through sample_layers.
+        let (height, humidity) = self.sample_lanes(
+            [world_tile_pos.x as f64; LANES],
+            [world_tile_pos.y as f64; LANES],
+        );
         TileData {
-            height: self
-                .height
-                .get(Self::get_point(x, y, self.values.resolution)) as f32,
-            humidity: self.biome.get(Self::get_point(x, y, 0.022)) as f32,
+            height: height[0],
+            humidity: humidity[0],
+            type_override: None,
+            decoration: None,
+        }
+    }
+
+    pub fn get_tile_data_grid(&self, origin: IVec2, size: UVec2) -> TerrainGrid {
+        let (height, humidity) = self.sample_layers(origin, size, 1);
+        TerrainGrid::new(origin, size, height, humidity)
+    }
+
+    // Height and humidity of size.x * size.y tiles that are `step` tiles apart, starting at origin,
+    // in row-major order. The same values get_tile_data returns for each of them, but sampled
+    // LANES tiles of a row at a time.
+    pub fn sample_layers(&self, origin: IVec2, size: UVec2, step: i32) -> (Vec<f32>, Vec<f32>) {
+        let len = (size.x * size.y) as usize;
+        let mut heights = Vec::with_capacity(len);
+        let mut humidities = Vec::with_capacity(len);
+        for y in 0..size.y as i32 {
+            let tile_y = (origin.y + y * step) as f64;
+            for x in (0..size.x as i32).step_by(LANES) {
+                let tile_x = std::array::from_fn(|i| (origin.x + (x + i as i32) * step) as f64);
+                let (height, humidity) = self.sample_lanes(tile_x, [tile_y; LANES]);
+                // The last lanes of a row that doesn't divide evenly are past its end.
+                let count = (size.x as usize - x as usize).min(LANES);
+                heights.extend_from_slice(&height[..count]);
+                humidities.extend_from_slice(&humidity[..count]);
+            }
+        }
+        (heights, humidities)
+    }
+
+    fn sample_lanes(&self, x: Lanes, y: Lanes) -> ([f32; LANES], [f32; LANES]) {
+        let resolution = self.values.resolution;
+        let height = self
+            .height
+            .get(x.map(|x| x * resolution), y.map(|y| y * resolution));
+        let humidity = self.biome.get(
+            x.map(|x| x * HUMIDITY_RESOLUTION),
+            y.map(|y| y * HUMIDITY_RESOLUTION),
+        );
+        (
+            height.map(|height| (height as f32 + self.values.height_offset).clamp(-1.0, 1.0)),
+            humidity
+                .map(|humidity| (humidity as f32 + self.values.humidity_offset).clamp(-1.0, 1.0)),
+        )
+    }
+}
+
+// 32-bit FNV-1a over the seed's bytes. Reflect::reflect_hash mixes in the TypeId, which changes
+// between compiler versions, so the same seed would make a different world depending on the build.
+fn hash_seed(seed: &str) -> u32 {
+    seed.bytes().fold(FNV_OFFSET_BASIS, |hash, byte| {
+        (hash ^ byte as u32).wrapping_mul(FNV_PRIME)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn generator(seed: &str) -> NoiseGenerator {
+        NoiseGenerator::new(&seed.to_string(), NoiseValues::default())
+    }
+
+    fn sample(noise: &NoiseGenerator) -> Vec<(f32, f32)> {
+        noise
+            .get_tile_data_grid(IVec2::new(-40, 17), UVec2::new(24, 24))
+            .tiles()
+            .map(|tile| (tile.height, tile.humidity))
+            .collect()
+    }
+
+    #[test]
+    fn same_seed_same_world() {
+        assert_eq!(generator("42").seed(), generator("42").seed());
+        assert_eq!(sample(&generator("42")), sample(&generator("42")));
+        assert_eq!(sample(&generator("abc")), sample(&generator("abc")));
+    }
+
+    #[test]
+    fn seeds_hash_the_same_everywhere() {
+        // Reference values of 32-bit FNV-1a.
+        assert_eq!(hash_seed(""), 0x811c_9dc5);
+        assert_eq!(hash_seed("a"), 0xe40c_292c);
+        assert_eq!(generator("foobar").seed(), 0xbf9c_f968);
+    }
+
+    #[test]
+    fn different_seeds_different_worlds() {
+        assert_ne!(generator("1").seed(), generator("2").seed());
+        assert_ne!(sample(&generator("1")), sample(&generator("2")));
+    }
+
+    #[test]
+    fn batch_sampling_matches_single_tiles() {
+        let noise = NoiseGenerator::new(
+            &"batch".to_string(),
+            NoiseValues {
+                resolution: 0.07,
+                height_offset: 0.2,
+                humidity_offset: -0.3,
+            },
+        );
+        let grid = noise.get_tile_data_grid(IVec2::new(-7, -300), UVec2::new(19, 11));
+        for (pos, tile) in grid.positions().zip(grid.tiles()) {
+            let single = noise.get_tile_data(pos);
+            assert_eq!(tile.height, single.height);
+            assert_eq!(tile.humidity, single.humidity);
+        }
+    }
+
+    #[test]
+    fn strided_sampling_skips_tiles() {
+        let noise = generator("stride");
+        let origin = IVec2::new(13, -50);
+        let (heights, humidities) = noise.sample_layers(origin, UVec2::new(6, 3), 5);
+        for y in 0..3 {
+            for x in 0..6 {
+                let single = noise.get_tile_data(origin + IVec2::new(x, y) * 5);
+                let i = (y * 6 + x) as usize;
+                assert_eq!(heights[i], single.height);
+                assert_eq!(humidities[i], single.humidity);
+            }
         }
     }
 
-    fn get_point(x: f64, y: f64, resolution: f64) -> [f64; 2] {
-        [x * resolution, y * resolution]
+    #[test]
+    fn offsets_are_clamped() {
+        let noise = NoiseGenerator::new(
+            &"42".to_string(),
+            NoiseValues {
+                height_offset: 5.0,
+                humidity_offset: -5.0,
+                ..default()
+            },
+        );
+        assert!(sample(&noise)
+            .iter()
+            .all(|(height, humidity)| *height == 1.0 && *humidity == -1.0));
     }
 }
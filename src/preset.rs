@@ -0,0 +1,150 @@
+use crate::noise_generator::{NoiseGenerator, NoiseValues};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::path::{Path, PathBuf};
+
+const BUILT_IN_PRESETS: [&str; 3] = [
+    include_str!("../assets/presets/archipelago.ron"),
+    include_str!("../assets/presets/pangea.ron"),
+    include_str!("../assets/presets/desert_world.ron"),
+];
+
+// Presets saved from the UI end up here, next to the executable's working directory.
+const USER_PRESET_DIR: &str = "presets";
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct GeneratorPreset {
+    pub name: String,
+    pub seed: String,
+    pub noise_values: NoiseValues,
+}
+
+impl Default for GeneratorPreset {
+    fn default() -> Self {
+        GeneratorPreset {
+            name: String::from("Default"),
+            seed: String::from("42"),
+            noise_values: NoiseValues::default(),
+        }
+    }
+}
+
+impl GeneratorPreset {
+    pub fn built_in() -> Vec<GeneratorPreset> {
+        BUILT_IN_PRESETS
+            .iter()
+            .map(|preset| ron::from_str(preset).expect("built-in presets should be valid"))
+            .collect()
+    }
+
+    pub fn noise_generator(&self) -> NoiseGenerator {
+        NoiseGenerator::new(&self.seed, self.noise_values)
+    }
+
+    // Files ending in .json are read as JSON, everything else as RON.
+    pub fn load(path: &Path) -> Result<GeneratorPreset, Box<dyn Error>> {
+        let content = std::fs::read_to_string(path)?;
+        if is_json(path) {
+            Ok(serde_json::from_str(&content)?)
+        } else {
+            Ok(ron::from_str(&content)?)
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        let content = if is_json(path) {
+            serde_json::to_string_pretty(self)?
+        } else {
+            ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())?
+        };
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, content)?;
+        Ok(())
+    }
+
+    // Accepts the name of a built-in or saved preset, or a path to a preset file.
+    pub fn find(name_or_path: &str) -> Result<GeneratorPreset, Box<dyn Error>> {
+        let path = Path::new(name_or_path);
+        if path.is_file() {
+            return GeneratorPreset::load(path);
+        }
+
+        Presets::default()
+            .presets
+            .into_iter()
+            .find(|preset| preset.name.eq_ignore_ascii_case(name_or_path))
+            .ok_or_else(|| format!("no preset named '{name_or_path}'").into())
+    }
+
+    pub fn user_preset_path(&self) -> PathBuf {
+        let file_name: String = self
+            .name
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect();
+        Path::new(USER_PRESET_DIR).join(file_name.to_lowercase() + ".ron")
+    }
+}
+
+fn is_json(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension == "json")
+}
+
+// Built-in presets followed by the ones saved in the user preset directory.
+#[derive(Resource)]
+pub struct Presets {
+    pub presets: Vec<GeneratorPreset>,
+}
+
+impl Default for Presets {
+    fn default() -> Self {
+        let mut presets = GeneratorPreset::built_in();
+        presets.extend(load_user_presets());
+        Presets { presets }
+    }
+}
+
+impl Presets {
+    // Replaces a preset with the same name, so saving twice doesn't duplicate it.
+    pub fn insert(&mut self, preset: GeneratorPreset) {
+        match self.presets.iter_mut().find(|p| p.name == preset.name) {
+            Some(existing) => *existing = preset,
+            None => self.presets.push(preset),
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn load_user_presets() -> Vec<GeneratorPreset> {
+    let Ok(entries) = std::fs::read_dir(USER_PRESET_DIR) else {
+        return Vec::new();
+    };
+
+    let mut paths: Vec<PathBuf> = entries
+        .filter_map(|entry| Some(entry.ok()?.path()))
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|extension| extension == "ron" || extension == "json")
+        })
+        .collect();
+    paths.sort();
+    paths
+        .iter()
+        .filter_map(|path| match GeneratorPreset::load(path) {
+            Ok(preset) => Some(preset),
+            Err(error) => {
+                warn!("Skipping preset {}: {error}", path.display());
+                None
+            }
+        })
+        .collect()
+}
+
+#[cfg(target_arch = "wasm32")]
+fn load_user_presets() -> Vec<GeneratorPreset> {
+    Vec::new()
+}
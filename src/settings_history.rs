@@ -0,0 +1,312 @@
+use crate::editor::Brush;
+use crate::game_ui::{RegenerateMapEvent, UnappliedSettings, LIVE_PREVIEW_DELAY};
+use crate::noise_generator::{NoiseGenerator, NoiseValues};
+use bevy::prelude::*;
+use bevy_egui::egui::Align2;
+use bevy_egui::*;
+use leafwing_input_manager::action_state::ActionState;
+use leafwing_input_manager::input_map::InputMap;
+use leafwing_input_manager::plugin::InputManagerPlugin;
+use leafwing_input_manager::prelude::UserInput;
+use leafwing_input_manager::{Actionlike, InputManagerBundle};
+
+// Oldest worlds are dropped once the history grows past this.
+const MAX_ENTRIES: usize = 50;
+
+pub struct SettingsHistoryPlugin;
+impl Plugin for SettingsHistoryPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(InputManagerPlugin::<HistoryAction>::default())
+            .init_resource::<SettingsHistory>()
+            .add_systems(Startup, init)
+            .add_systems(Update, record_settings)
+            .add_systems(Update, history_ui_system.after(record_settings));
+    }
+}
+
+#[derive(Actionlike, PartialEq, Eq, Hash, Clone, Copy, Debug, Reflect)]
+pub enum HistoryAction {
+    Undo,
+    Redo,
+}
+
+struct SettingsEntry {
+    seed: String,
+    values: NoiseValues,
+}
+
+impl SettingsEntry {
+    fn matches(&self, noise: &NoiseGenerator) -> bool {
+        self.seed == noise.seed_text() && self.values == noise.values
+    }
+
+    fn label(&self) -> String {
+        format!(
+            "{} | {:.3} | {:+.2} | {:+.2}",
+            self.seed,
+            self.values.resolution,
+            self.values.height_offset,
+            self.values.humidity_offset
+        )
+    }
+}
+
+// Every generator configuration that was shown, oldest first. Undoing only moves `current`, so
+// the newer entries stay around for redo until another configuration is recorded.
+#[derive(Resource, Default)]
+struct SettingsHistory {
+    entries: Vec<SettingsEntry>,
+    current: usize,
+}
+
+#[derive(Copy, Clone)]
+enum HistoryStep {
+    Undo,
+    Redo,
+    Select(usize),
+}
+
+impl SettingsHistory {
+    fn is_current(&self, noise: &NoiseGenerator) -> bool {
+        self.entries
+            .get(self.current)
+            .is_some_and(|entry| entry.matches(noise))
+    }
+
+    // Adds the generator after the current entry, dropping the entries that could be redone.
+    // Returns how many of the oldest entries were dropped to make room.
+    fn record(&mut self, noise: &NoiseGenerator) -> usize {
+        let next = if self.entries.is_empty() {
+            0
+        } else {
+            self.current + 1
+        };
+        self.entries.truncate(next);
+        self.entries.push(SettingsEntry {
+            seed: noise.seed_text().to_string(),
+            values: noise.values,
+        });
+        let dropped = self.entries.len().saturating_sub(MAX_ENTRIES);
+        self.entries.drain(..dropped);
+        self.current = self.entries.len() - 1;
+        dropped
+    }
+
+    fn can_undo(&self, noise: &NoiseGenerator) -> bool {
+        self.current > 0 || (!self.entries.is_empty() && !self.is_current(noise))
+    }
+
+    fn can_redo(&self, noise: &NoiseGenerator) -> bool {
+        self.is_current(noise) && self.current + 1 < self.entries.len()
+    }
+
+    // Moves to the entry the step leads to and returns it, if it isn't the current one. A world
+    // that is shown but wasn't recorded yet, e.g. from the live preview, gets its entry first, so
+    // undo goes back to the world before it and redo returns to it.
+    fn step(&mut self, noise: &NoiseGenerator, step: HistoryStep) -> Option<&SettingsEntry> {
+        let mut dropped = 0;
+        if !self.entries.is_empty() && !self.is_current(noise) {
+            dropped = self.record(noise);
+        }
+        let index = match step {
+            HistoryStep::Undo => self.current.checked_sub(1)?,
+            HistoryStep::Redo => self.current + 1,
+            HistoryStep::Select(index) => index.checked_sub(dropped)?,
+        };
+        if index == self.current || index >= self.entries.len() {
+            return None;
+        }
+        self.current = index;
+        Some(&self.entries[index])
+    }
+}
+
+fn init(mut commands: Commands) {
+    commands.spawn(InputManagerBundle::<HistoryAction> {
+        input_map: default_input_map_history(),
+        ..default()
+    });
+}
+
+// Records the generator right away whenever a window regenerates the map with it: applying the
+// settings, loading a preset, world code or save. Anything else, like the live preview, only gets
+// recorded once the generator stayed the same for as long as the preview waits, so the steps in
+// between while dragging a slider don't each get an entry.
+fn record_settings(
+    time: Res<Time>,
+    noise: Res<NoiseGenerator>,
+    mut history: ResMut<SettingsHistory>,
+    mut regenerate_map_event: EventReader<RegenerateMapEvent>,
+    mut changed_at: Local<f32>,
+) {
+    if noise.is_changed() {
+        *changed_at = time.elapsed_seconds();
+    }
+    let regenerated = regenerate_map_event.read().count() > 0;
+    let settled = time.elapsed_seconds() - *changed_at >= LIVE_PREVIEW_DELAY;
+    if (regenerated || settled || history.entries.is_empty()) && !history.is_current(&noise) {
+        history.record(&noise);
+    }
+}
+
+fn history_ui_system(
+    mut contexts: EguiContexts,
+    mut history: ResMut<SettingsHistory>,
+    mut noise: ResMut<NoiseGenerator>,
+    mut unapplied_settings: ResMut<UnappliedSettings>,
+    mut regenerate_map_event: EventWriter<RegenerateMapEvent>,
+    brush: Res<Brush>,
+    action_query: Query<&ActionState<HistoryAction>>,
+) {
+    let mut step = None;
+    egui::Window::new("History")
+        .default_open(false)
+        .resizable(false)
+        .anchor(Align2::RIGHT_CENTER, egui::Vec2::new(-5.0, 0.0))
+        .show(contexts.ctx_mut(), |ui| {
+            ui.horizontal(|ui| {
+                if ui
+                    .add_enabled(history.can_undo(&noise), egui::Button::new("Undo"))
+                    .clicked()
+                {
+                    step = Some(HistoryStep::Undo);
+                }
+                if ui
+                    .add_enabled(history.can_redo(&noise), egui::Button::new("Redo"))
+                    .clicked()
+                {
+                    step = Some(HistoryStep::Redo);
+                }
+            });
+            ui.separator();
+            ui.label("Seed | Resolution | Height | Humidity");
+            egui::ScrollArea::vertical()
+                .max_height(200.0)
+                .show(ui, |ui| {
+                    for (i, entry) in history.entries.iter().enumerate().rev() {
+                        if ui
+                            .selectable_label(i == history.current, entry.label())
+                            .clicked()
+                        {
+                            step = Some(HistoryStep::Select(i));
+                        }
+                    }
+                });
+        });
+
+    // While painting, the same shortcuts undo tile edits instead.
+    if !brush.is_editing() && !contexts.ctx_mut().wants_keyboard_input() {
+        if let Ok(action_state) = action_query.get_single() {
+            if action_state.just_pressed(HistoryAction::Undo) {
+                step = Some(HistoryStep::Undo);
+            } else if action_state.just_pressed(HistoryAction::Redo) {
+                step = Some(HistoryStep::Redo);
+            }
+        }
+    }
+
+    let Some(entry) = step.and_then(|step| history.step(&noise, step)) else {
+        return;
+    };
+    unapplied_settings.seed = entry.seed.clone();
+    unapplied_settings.map_generator_values = entry.values;
+    #[cfg(target_arch = "wasm32")]
+    crate::world_code::update_url(&crate::world_code::encode(&entry.seed, &entry.values));
+    noise.set_if_neq(NoiseGenerator::new(&entry.seed, entry.values));
+    regenerate_map_event.send(RegenerateMapEvent);
+}
+
+fn default_input_map_history() -> InputMap<HistoryAction> {
+    let mut input_map = InputMap::default();
+    for modifier in [
+        KeyCode::ControlLeft,
+        KeyCode::ControlRight,
+        KeyCode::SuperLeft,
+    ] {
+        input_map.insert(
+            UserInput::chord([modifier, KeyCode::Z]),
+            HistoryAction::Undo,
+        );
+        input_map.insert(
+            UserInput::chord([modifier, KeyCode::Y]),
+            HistoryAction::Redo,
+        );
+        input_map.insert(
+            UserInput::chord([modifier, KeyCode::ShiftLeft, KeyCode::Z]),
+            HistoryAction::Redo,
+        );
+    }
+    input_map
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn generator(seed: &str) -> NoiseGenerator {
+        NoiseGenerator::new(&seed.to_string(), NoiseValues::default())
+    }
+
+    fn app() -> App {
+        let mut app = App::new();
+        app.init_resource::<Time>()
+            .init_resource::<NoiseGenerator>()
+            .init_resource::<SettingsHistory>()
+            .add_event::<RegenerateMapEvent>()
+            .add_systems(Update, record_settings);
+        app
+    }
+
+    // What the live preview does, it doesn't regenerate the map.
+    fn preview(app: &mut App, seed: &str) {
+        *app.world.resource_mut::<NoiseGenerator>() = generator(seed);
+        app.update();
+    }
+
+    fn wait(app: &mut App, seconds: f32) {
+        app.world
+            .resource_mut::<Time>()
+            .advance_by(Duration::from_secs_f32(seconds));
+        app.update();
+    }
+
+    fn undo(app: &mut App) -> Option<String> {
+        let noise = app.world.resource::<NoiseGenerator>().clone();
+        let mut history = app.world.resource_mut::<SettingsHistory>();
+        history
+            .step(&noise, HistoryStep::Undo)
+            .map(|entry| entry.seed.clone())
+    }
+
+    #[test]
+    fn previews_are_recorded_once_settled() {
+        let mut app = app();
+        app.update();
+        preview(&mut app, "first");
+        preview(&mut app, "second");
+        assert_eq!(app.world.resource::<SettingsHistory>().entries.len(), 1);
+
+        wait(&mut app, LIVE_PREVIEW_DELAY * 2.0);
+        let history = app.world.resource::<SettingsHistory>();
+        assert_eq!(history.entries.len(), 2);
+        assert!(history.entries[1].matches(&generator("second")));
+        assert_eq!(undo(&mut app).as_deref(), Some("42"));
+    }
+
+    #[test]
+    fn undo_returns_to_the_world_before_an_unsettled_preview() {
+        let mut app = app();
+        app.update();
+        preview(&mut app, "settled");
+        wait(&mut app, LIVE_PREVIEW_DELAY * 2.0);
+        preview(&mut app, "previewed");
+
+        assert_eq!(undo(&mut app).as_deref(), Some("settled"));
+        let noise = generator("settled");
+        let mut history = app.world.resource_mut::<SettingsHistory>();
+        assert!(history.can_redo(&noise));
+        let redone = history.step(&noise, HistoryStep::Redo).unwrap();
+        assert!(redone.matches(&generator("previewed")));
+    }
+}
@@ -1,8 +1,9 @@
 use crate::biome;
 use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
 use std::fmt::Formatter;
 
-#[derive(Debug)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub enum TileType {
     DeepWater,
     Water,
@@ -11,32 +12,346 @@ pub enum TileType {
     Stone,
 }
 
-#[derive(Component)]
+impl TileType {
+    pub const ALL: [TileType; 5] = [
+        TileType::DeepWater,
+        TileType::Water,
+        TileType::Sand,
+        TileType::Grass,
+        TileType::Stone,
+    ];
+
+    // Flat colors for anything that draws the map without tile sprites.
+    pub fn get_map_color(&self) -> Color {
+        match self {
+            TileType::DeepWater => Color::rgb_u8(31, 0, 177),
+            TileType::Water => Color::rgb_u8(44, 0, 255),
+            TileType::Sand => Color::rgb_u8(238, 195, 154),
+            TileType::Grass => Color::rgb_u8(0, 103, 0),
+            TileType::Stone => Color::rgb_u8(86, 86, 86),
+        }
+    }
+}
+
+// Placed on top of tiles by hand, the generator doesn't create any of these yet.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Decoration {
+    Tree,
+}
+
+impl Decoration {
+    pub const ALL: [Decoration; 1] = [Decoration::Tree];
+
+    pub fn texture(&self) -> &'static str {
+        match self {
+            Decoration::Tree => "sprites/tree.png",
+        }
+    }
+
+    // Size in world units, anchored at the bottom center of the tile.
+    pub fn size(&self) -> Vec2 {
+        match self {
+            Decoration::Tree => Vec2::new(24.0, 40.0),
+        }
+    }
+}
+
+#[derive(Copy, Clone)]
 pub struct TileData {
     pub height: f32,
     pub humidity: f32,
+    pub type_override: Option<TileType>,
+    pub decoration: Option<Decoration>,
 }
 impl TileData {
     pub fn get_tile_type(&self) -> TileType {
-        if self.humidity < 0.0 {
-            biome::REGULAR.evaluate_multibiome(&biome::WET, self.height, 1.0 + self.humidity)
-        } else {
-            biome::REGULAR.evaluate_multibiome(&biome::DRY, self.height, 1.0 - self.humidity)
+        if let Some(tile_type) = self.type_override {
+            return tile_type;
         }
+
+        let other = if self.humidity < 0.0 {
+            &biome::WET
+        } else {
+            &biome::DRY
+        };
+        biome::REGULAR.evaluate_multibiome(other, self.height, self.get_biome_weight())
+    }
+
+    // How strongly the regular biome applies, as opposed to the wet or dry one.
+    pub fn get_biome_weight(&self) -> f32 {
+        1.0 - self.humidity.abs()
+    }
+
+    // There is no temperature noise, so this is derived: high and wet tiles are colder.
+    pub fn get_temperature(&self) -> f32 {
+        (0.6 - self.height.max(0.0) * 0.8 + self.humidity * 0.3).clamp(0.0, 1.0)
+    }
+
+    // Slightly darkens low tiles and shifts dry tiles towards warmer colors.
+    pub fn get_tint(&self) -> Color {
+        let brightness = 0.85 + 0.15 * (self.height + 1.0) * 0.5;
+        let dryness = self.humidity.clamp(0.0, 1.0);
+        Color::rgb(
+            brightness,
+            brightness * (1.0 - 0.05 * dryness),
+            brightness * (1.0 - 0.15 * dryness),
+        )
     }
 
-    pub fn get_color(&self) -> Color {
-        Color::rgb(0.0, (self.height + 1.0) * 0.5, 0.0)
+    // Hillshaded brightness, with wet tiles leaning towards blue-green and dry ones towards yellow.
+    pub fn get_color(&self, hillshade: f32) -> Color {
+        let wetness = (-self.humidity).clamp(0.0, 1.0);
+        let dryness = self.humidity.clamp(0.0, 1.0);
+        let brightness = hillshade.clamp(0.0, 1.0);
+        Color::rgb(
+            brightness * (1.0 - 0.2 * wetness),
+            brightness,
+            brightness * (1.0 - 0.3 * dryness),
+        )
     }
 }
 impl std::fmt::Display for TileData {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "Height: {}\nHumidity: {}\n-> {:?}",
+            "Height: {}\nHumidity: {}\n-> {:?}{}{}",
             self.height,
             self.humidity,
-            self.get_tile_type()
+            self.get_tile_type(),
+            if self.type_override.is_some() {
+                " (overridden)"
+            } else {
+                ""
+            },
+            match self.decoration {
+                Some(decoration) => format!("\nDecoration: {decoration:?}"),
+                None => String::new(),
+            }
         )
     }
 }
+
+pub struct TileGrid<T> {
+    origin: IVec2,
+    size: UVec2,
+    tiles: Vec<T>,
+}
+impl<T> TileGrid<T> {
+    pub fn new(origin: IVec2, size: UVec2, tiles: Vec<T>) -> Self {
+        debug_assert_eq!(tiles.len(), (size.x * size.y) as usize);
+        TileGrid {
+            origin,
+            size,
+            tiles,
+        }
+    }
+
+    pub fn origin(&self) -> IVec2 {
+        self.origin
+    }
+
+    pub fn size(&self) -> UVec2 {
+        self.size
+    }
+
+    pub fn contains(&self, world_tile_pos: IVec2) -> bool {
+        let local = world_tile_pos - self.origin;
+        local.x >= 0 && local.y >= 0 && local.x < self.size.x as i32 && local.y < self.size.y as i32
+    }
+
+    pub fn get(&self, world_tile_pos: IVec2) -> &T {
+        let local = (world_tile_pos - self.origin).as_uvec2();
+        &self.tiles[(local.y * self.size.x + local.x) as usize]
+    }
+
+    pub fn get_mut(&mut self, world_tile_pos: IVec2) -> &mut T {
+        let local = (world_tile_pos - self.origin).as_uvec2();
+        &mut self.tiles[(local.y * self.size.x + local.x) as usize]
+    }
+
+    // Row-major, in the same order as the values passed to new().
+    pub fn positions(&self) -> impl Iterator<Item = IVec2> + '_ {
+        (0..self.size.y as i32)
+            .flat_map(move |y| (0..self.size.x as i32).map(move |x| self.origin + IVec2::new(x, y)))
+    }
+
+    pub fn values(&self) -> &[T] {
+        &self.tiles
+    }
+}
+impl<T: Clone> TileGrid<T> {
+    pub fn filled(origin: IVec2, size: UVec2, value: T) -> Self {
+        TileGrid::new(origin, size, vec![value; (size.x * size.y) as usize])
+    }
+
+    // The part of the grid covered by origin and size, which has to lie within it.
+    pub fn crop(&self, origin: IVec2, size: UVec2) -> Self {
+        let mut tiles = Vec::with_capacity((size.x * size.y) as usize);
+        for y in 0..size.y as i32 {
+            let start = (origin + IVec2::new(0, y) - self.origin).as_uvec2();
+            let start = (start.y * self.size.x + start.x) as usize;
+            tiles.extend_from_slice(&self.tiles[start..start + size.x as usize]);
+        }
+        TileGrid::new(origin, size, tiles)
+    }
+}
+
+// Tiles of an area stored as one grid per layer, the way the noise generator samples them.
+// Anything that only needs some of the layers can use those directly instead of going through
+// TileData for every tile.
+pub struct TerrainGrid {
+    height: TileGrid<f32>,
+    humidity: TileGrid<f32>,
+    type_override: TileGrid<Option<TileType>>,
+    decoration: TileGrid<Option<Decoration>>,
+}
+impl TerrainGrid {
+    // Generated terrain, without any overrides or decorations.
+    pub fn new(origin: IVec2, size: UVec2, height: Vec<f32>, humidity: Vec<f32>) -> Self {
+        TerrainGrid {
+            height: TileGrid::new(origin, size, height),
+            humidity: TileGrid::new(origin, size, humidity),
+            type_override: TileGrid::filled(origin, size, None),
+            decoration: TileGrid::filled(origin, size, None),
+        }
+    }
+
+    pub fn origin(&self) -> IVec2 {
+        self.height.origin()
+    }
+
+    pub fn size(&self) -> UVec2 {
+        self.height.size()
+    }
+
+    pub fn contains(&self, world_tile_pos: IVec2) -> bool {
+        self.height.contains(world_tile_pos)
+    }
+
+    pub fn positions(&self) -> impl Iterator<Item = IVec2> + '_ {
+        self.height.positions()
+    }
+
+    pub fn height(&self) -> &TileGrid<f32> {
+        &self.height
+    }
+
+    pub fn humidity(&self) -> &TileGrid<f32> {
+        &self.humidity
+    }
+
+    pub fn get(&self, world_tile_pos: IVec2) -> TileData {
+        TileData {
+            height: *self.height.get(world_tile_pos),
+            humidity: *self.humidity.get(world_tile_pos),
+            type_override: *self.type_override.get(world_tile_pos),
+            decoration: *self.decoration.get(world_tile_pos),
+        }
+    }
+
+    pub fn set(&mut self, world_tile_pos: IVec2, tile_data: TileData) {
+        *self.height.get_mut(world_tile_pos) = tile_data.height;
+        *self.humidity.get_mut(world_tile_pos) = tile_data.humidity;
+        *self.type_override.get_mut(world_tile_pos) = tile_data.type_override;
+        *self.decoration.get_mut(world_tile_pos) = tile_data.decoration;
+    }
+
+    // Row-major, in the same order as positions().
+    pub fn tiles(&self) -> impl Iterator<Item = TileData> + '_ {
+        self.positions().map(|pos| self.get(pos))
+    }
+
+    pub fn crop(&self, origin: IVec2, size: UVec2) -> Self {
+        TerrainGrid {
+            height: self.height.crop(origin, size),
+            humidity: self.humidity.crop(origin, size),
+            type_override: self.type_override.crop(origin, size),
+            decoration: self.decoration.crop(origin, size),
+        }
+    }
+
+    // Central differences, so the grid needs one tile of padding around the given position.
+    pub fn height_gradient(&self, world_tile_pos: IVec2) -> Vec2 {
+        let height = |offset: IVec2| *self.height.get(world_tile_pos + offset);
+        Vec2::new(
+            (height(IVec2::X) - height(IVec2::NEG_X)) * 0.5,
+            (height(IVec2::Y) - height(IVec2::NEG_Y)) * 0.5,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tile(height: f32, humidity: f32) -> TileData {
+        TileData {
+            height,
+            humidity,
+            type_override: None,
+            decoration: None,
+        }
+    }
+
+    #[test]
+    fn humidity_picks_the_biome() {
+        assert_eq!(tile(0.0, 0.0).get_tile_type(), TileType::Grass);
+        assert_eq!(tile(0.0, -1.0).get_tile_type(), TileType::Water);
+        assert_eq!(tile(0.0, 1.0).get_tile_type(), TileType::Sand);
+    }
+
+    #[test]
+    fn biomes_blend_with_humidity() {
+        assert_eq!(tile(-0.05, 0.0).get_tile_type(), TileType::Grass);
+        assert_eq!(tile(-0.05, -0.5).get_tile_type(), TileType::Sand);
+        assert_eq!(tile(-0.05, -1.0).get_tile_type(), TileType::Water);
+    }
+
+    #[test]
+    fn overrides_win() {
+        let mut tile_data = tile(-1.0, -1.0);
+        tile_data.type_override = Some(TileType::Stone);
+        assert_eq!(tile_data.get_tile_type(), TileType::Stone);
+    }
+
+    #[test]
+    fn grid_positions_match_values() {
+        let origin = IVec2::new(-2, 5);
+        let grid = TileGrid::new(origin, UVec2::new(3, 2), (0..6).collect());
+        for (pos, value) in grid.positions().zip(grid.values()) {
+            assert!(grid.contains(pos));
+            assert_eq!(grid.get(pos), value);
+        }
+        assert_eq!(*grid.get(origin + IVec2::new(2, 1)), 5);
+        assert!(!grid.contains(origin - IVec2::X));
+        assert!(!grid.contains(origin + IVec2::new(3, 0)));
+    }
+
+    #[test]
+    fn cropping_keeps_positions() {
+        let grid = TileGrid::new(IVec2::new(-2, 5), UVec2::new(4, 3), (0..12).collect());
+        let cropped = grid.crop(IVec2::new(-1, 6), UVec2::new(2, 2));
+        assert_eq!(cropped.values(), &[5, 6, 9, 10]);
+        for pos in cropped.positions() {
+            assert_eq!(cropped.get(pos), grid.get(pos));
+        }
+    }
+
+    #[test]
+    fn terrain_layers_round_trip() {
+        let mut terrain = TerrainGrid::new(
+            IVec2::ZERO,
+            UVec2::new(2, 1),
+            vec![0.1, 0.2],
+            vec![0.3, 0.4],
+        );
+        let mut tile_data = terrain.get(IVec2::X);
+        assert_eq!((tile_data.height, tile_data.humidity), (0.2, 0.4));
+        tile_data.type_override = Some(TileType::Stone);
+        tile_data.decoration = Some(Decoration::Tree);
+        terrain.set(IVec2::X, tile_data);
+        assert_eq!(terrain.get(IVec2::X).get_tile_type(), TileType::Stone);
+        assert_eq!(terrain.get(IVec2::X).decoration, Some(Decoration::Tree));
+        assert_eq!(terrain.get(IVec2::ZERO).decoration, None);
+    }
+}
@@ -0,0 +1,288 @@
+use crate::coords::tile_to_chunk_pos;
+use crate::noise_generator::{NoiseGenerator, NoiseValues};
+use crate::tile_data::{Decoration, TerrainGrid, TileData, TileType};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+// Changes made to tiles after generating them, e.g. painted in the editor or imported from Tiled.
+// Chunks are regenerated from the noise whenever they are spawned, so these get applied on top
+// every time.
+#[derive(Copy, Clone, Default, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TileDelta {
+    pub tile_type: Option<TileType>,
+    pub height: Option<f32>,
+    pub humidity: Option<f32>,
+    pub decoration: Option<Decoration>,
+}
+
+impl TileDelta {
+    pub fn is_empty(&self) -> bool {
+        *self == TileDelta::default()
+    }
+
+    pub fn apply(&self, tile_data: &mut TileData) {
+        if let Some(height) = self.height {
+            tile_data.height = height;
+        }
+        if let Some(humidity) = self.humidity {
+            tile_data.humidity = humidity;
+        }
+        if self.tile_type.is_some() {
+            tile_data.type_override = self.tile_type;
+        }
+        if self.decoration.is_some() {
+            tile_data.decoration = self.decoration;
+        }
+    }
+}
+
+type ChunkMap = HashMap<IVec2, HashMap<IVec2, TileDelta>>;
+
+// Deltas grouped by chunk, so applying them to a freshly sampled chunk only looks at the chunks
+// it overlaps instead of every modified tile in the world.
+#[derive(Resource, Clone, Default)]
+pub struct TileDeltas {
+    chunks: ChunkMap,
+    // Any other generator creates different terrain underneath the deltas, so they don't carry
+    // over. They are put aside instead, until that generator is used again.
+    other_generators: GeneratorShelf<ChunkMap>,
+}
+
+// Seed and values of a generator.
+type GeneratorKey = (u32, NoiseValues);
+
+fn generator_of(noise: &NoiseGenerator) -> GeneratorKey {
+    (noise.seed(), noise.values)
+}
+
+// Whatever was made on the generators that aren't in use, so switching back to one of them, e.g.
+// by undoing a settings change, brings it back.
+#[derive(Clone)]
+pub struct GeneratorShelf<T> {
+    current: Option<GeneratorKey>,
+    shelved: Vec<(GeneratorKey, T)>,
+}
+
+impl<T> Default for GeneratorShelf<T> {
+    fn default() -> Self {
+        GeneratorShelf {
+            current: None,
+            shelved: Vec::new(),
+        }
+    }
+}
+
+impl<T: Default> GeneratorShelf<T> {
+    pub fn new(noise: &NoiseGenerator) -> Self {
+        GeneratorShelf {
+            current: Some(generator_of(noise)),
+            shelved: Vec::new(),
+        }
+    }
+
+    // Puts value aside for the previous generator if keep is set, and replaces it with what was
+    // put aside for this one. The first generator just adopts the value. Returns whether the
+    // generator changed.
+    pub fn switch(&mut self, noise: &NoiseGenerator, value: &mut T, keep: bool) -> bool {
+        let generator = generator_of(noise);
+        let previous = match self.current.replace(generator) {
+            Some(previous) if previous != generator => previous,
+            _ => return false,
+        };
+
+        let restored = match self.shelved.iter().position(|(key, _)| *key == generator) {
+            Some(index) => self.shelved.swap_remove(index).1,
+            None => T::default(),
+        };
+        let shelved = std::mem::replace(value, restored);
+        if keep {
+            self.shelved.push((previous, shelved));
+        }
+        true
+    }
+}
+
+// Serialized form of a single chunk, since the map keys can't be written as RON directly.
+#[derive(Serialize, Deserialize)]
+pub struct ChunkDeltas {
+    pub chunk: (i32, i32),
+    pub tiles: Vec<((i32, i32), TileDelta)>,
+}
+
+impl TileDeltas {
+    pub fn get(&self, world_tile_pos: IVec2) -> TileDelta {
+        self.chunks
+            .get(&tile_to_chunk_pos(world_tile_pos))
+            .and_then(|chunk| chunk.get(&world_tile_pos))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    pub fn set(&mut self, world_tile_pos: IVec2, delta: TileDelta) {
+        self.modify(world_tile_pos, |existing| *existing = delta);
+    }
+
+    // The tile as it currently is, with its delta applied.
+    pub fn tile_data(&self, noise: &NoiseGenerator, world_tile_pos: IVec2) -> TileData {
+        let mut tile_data = noise.get_tile_data(world_tile_pos);
+        self.get(world_tile_pos).apply(&mut tile_data);
+        tile_data
+    }
+
+    // Changes the delta of a tile in place, dropping it again once nothing is left to apply.
+    pub fn modify(&mut self, world_tile_pos: IVec2, f: impl FnOnce(&mut TileDelta)) {
+        let chunk_pos = tile_to_chunk_pos(world_tile_pos);
+        let chunk = self.chunks.entry(chunk_pos).or_default();
+        let delta = chunk.entry(world_tile_pos).or_default();
+        f(delta);
+        if delta.is_empty() {
+            chunk.remove(&world_tile_pos);
+            if chunk.is_empty() {
+                self.chunks.remove(&chunk_pos);
+            }
+        }
+    }
+
+    pub fn set_tile_type(&mut self, world_tile_pos: IVec2, tile_type: TileType) {
+        self.modify(world_tile_pos, |delta| delta.tile_type = Some(tile_type));
+    }
+
+    pub fn clear(&mut self) {
+        self.chunks.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.chunks.values().map(HashMap::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.chunks.is_empty()
+    }
+
+    // Chunks of deltas overlapping the given area of tiles.
+    fn chunks_in(
+        &self,
+        origin: IVec2,
+        size: UVec2,
+    ) -> impl Iterator<Item = (IVec2, &HashMap<IVec2, TileDelta>)> {
+        let min = tile_to_chunk_pos(origin);
+        let max = tile_to_chunk_pos(origin + size.as_ivec2() - IVec2::ONE);
+        (min.y..=max.y)
+            .flat_map(move |y| (min.x..=max.x).map(move |x| IVec2::new(x, y)))
+            .filter_map(|chunk_pos| Some((chunk_pos, self.chunks.get(&chunk_pos)?)))
+    }
+
+    pub fn apply(&self, tiles: &mut TerrainGrid) {
+        if self.is_empty() {
+            return;
+        }
+
+        for (_, chunk) in self.chunks_in(tiles.origin(), tiles.size()) {
+            for (world_tile_pos, delta) in chunk {
+                if tiles.contains(*world_tile_pos) {
+                    let mut tile_data = tiles.get(*world_tile_pos);
+                    delta.apply(&mut tile_data);
+                    tiles.set(*world_tile_pos, tile_data);
+                }
+            }
+        }
+    }
+
+    // Copy of the deltas overlapping an area, small enough to hand to a chunk generation task.
+    pub fn region(&self, origin: IVec2, size: UVec2) -> TileDeltas {
+        TileDeltas {
+            chunks: self
+                .chunks_in(origin, size)
+                .map(|(chunk_pos, chunk)| (chunk_pos, chunk.clone()))
+                .collect(),
+            ..default()
+        }
+    }
+
+    pub fn to_chunks(&self) -> Vec<ChunkDeltas> {
+        let mut chunks: Vec<ChunkDeltas> = self
+            .chunks
+            .iter()
+            .map(|(chunk_pos, tiles)| {
+                let mut tiles: Vec<_> = tiles
+                    .iter()
+                    .map(|(pos, delta)| ((pos.x, pos.y), *delta))
+                    .collect();
+                tiles.sort_by_key(|(pos, _)| (pos.1, pos.0));
+                ChunkDeltas {
+                    chunk: (chunk_pos.x, chunk_pos.y),
+                    tiles,
+                }
+            })
+            .collect();
+        // Sorted so saving the same world twice produces the same file.
+        chunks.sort_by_key(|chunk| (chunk.chunk.1, chunk.chunk.0));
+        chunks
+    }
+
+    // Tiles are filed by their own position, in case a file was edited by hand.
+    pub fn from_chunks(noise: &NoiseGenerator, chunks: Vec<ChunkDeltas>) -> Self {
+        let mut deltas = TileDeltas {
+            other_generators: GeneratorShelf::new(noise),
+            ..default()
+        };
+        for (pos, delta) in chunks.into_iter().flat_map(|chunk| chunk.tiles) {
+            deltas.set(IVec2::new(pos.0, pos.1), delta);
+        }
+        deltas
+    }
+
+    // Puts the deltas aside if they were made on another generator, and brings back the ones
+    // made on this one. Returns whether the deltas changed.
+    pub fn switch_generator(&mut self, noise: &NoiseGenerator) -> bool {
+        let had_deltas = !self.is_empty();
+        self.other_generators
+            .switch(noise, &mut self.chunks, had_deltas)
+            && (had_deltas || !self.is_empty())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn generator(seed: &str) -> NoiseGenerator {
+        NoiseGenerator::new(&seed.to_string(), NoiseValues::default())
+    }
+
+    #[test]
+    fn deltas_stay_with_their_generator() {
+        let mut deltas = TileDeltas::default();
+        assert!(!deltas.switch_generator(&generator("1")));
+        deltas.set_tile_type(IVec2::ZERO, TileType::Stone);
+        assert!(!deltas.switch_generator(&generator("1")));
+        assert_eq!(deltas.len(), 1);
+        assert!(deltas.switch_generator(&generator("2")));
+        assert!(deltas.is_empty());
+        // Nothing to put aside or bring back.
+        assert!(!deltas.switch_generator(&generator("3")));
+        assert!(deltas.switch_generator(&generator("1")));
+        assert_eq!(deltas.get(IVec2::ZERO).tile_type, Some(TileType::Stone));
+        assert!(deltas.switch_generator(&generator("2")));
+        assert!(deltas.is_empty());
+    }
+
+    #[test]
+    fn loaded_deltas_belong_to_their_generator() {
+        let chunks = vec![ChunkDeltas {
+            chunk: (0, 0),
+            tiles: vec![(
+                (1, 2),
+                TileDelta {
+                    tile_type: Some(TileType::Sand),
+                    ..default()
+                },
+            )],
+        }];
+        let mut deltas = TileDeltas::from_chunks(&generator("1"), chunks);
+        assert!(!deltas.switch_generator(&generator("1")));
+        assert_eq!(deltas.get(IVec2::new(1, 2)).tile_type, Some(TileType::Sand));
+    }
+}
@@ -0,0 +1,726 @@
+use crate::autotile::Tileset;
+use crate::export::{self, TileRegion};
+use crate::game_map::{TilePainter, TILE_SIZE};
+use crate::noise_generator::{NoiseGenerator, NoiseValues};
+use crate::tile_data::Decoration;
+use crate::tile_deltas::TileDeltas;
+use crate::view_mode::ViewMode;
+use crate::world_code;
+use bevy::prelude::*;
+use bevy_ecs_tilemap::prelude::*;
+use serde_json::{json, Value};
+use std::error::Error;
+use std::fmt::Write;
+use std::path::{Component, Path};
+
+// Exports regions as maps for the Tiled editor (https://www.mapeditor.org/) and imports the
+// terrain and decoration layers back as tile deltas. Tiled has no per-tile custom properties, so
+// height and humidity are stored as CSV string properties on the terrain layer, in the same order
+// as its tiles.
+
+const TERRAIN_LAYER: &str = "terrain";
+const DECORATION_LAYER: &str = "decorations";
+const TERRAIN_TILESET: &str = "terrain";
+const DECORATION_TILESET: &str = "decorations";
+// Tiled stores flips and rotations in the upper bits of a global tile id.
+const GID_FLAGS: u32 = 0xE000_0000;
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum TiledFormat {
+    Tmx,
+    Tmj,
+}
+
+impl TiledFormat {
+    pub fn from_path(path: &Path) -> Option<TiledFormat> {
+        match path.extension()?.to_str()? {
+            "tmx" => Some(TiledFormat::Tmx),
+            "tmj" | "json" => Some(TiledFormat::Tmj),
+            _ => None,
+        }
+    }
+}
+
+// Tiles are stored row by row, starting at the top left like Tiled does.
+struct TiledMap {
+    region: TileRegion,
+    // As entered, so whoever imports the map can tell which world it belongs to.
+    seed: String,
+    noise_values: NoiseValues,
+    terrain: Vec<u32>,
+    decorations: Vec<u32>,
+    // Decorations are one image per tile, numbered in the order of Decoration::ALL from here.
+    first_decoration_gid: u32,
+    heights: Vec<f32>,
+    humidities: Vec<f32>,
+}
+
+impl TiledMap {
+    fn index(&self, world_tile_pos: IVec2) -> usize {
+        let local = world_tile_pos - self.region.origin;
+        let row = self.region.size.y as i32 - 1 - local.y;
+        (row * self.region.size.x as i32 + local.x) as usize
+    }
+
+    fn world_tile_pos(&self, index: usize) -> IVec2 {
+        let width = self.region.size.x as usize;
+        let row = (index / width) as i32;
+        let column = (index % width) as i32;
+        self.region.origin + IVec2::new(column, self.region.size.y as i32 - 1 - row)
+    }
+
+    fn decoration_gid(&self, decoration: Option<Decoration>) -> u32 {
+        let Some(decoration) = decoration else {
+            return 0;
+        };
+        let index = Decoration::ALL
+            .iter()
+            .position(|other| *other == decoration)
+            .unwrap_or_default();
+        self.first_decoration_gid + index as u32
+    }
+
+    fn decoration(&self, gid: u32) -> Option<Decoration> {
+        let index = (gid & !GID_FLAGS).checked_sub(self.first_decoration_gid)?;
+        Decoration::ALL.get(index as usize).copied()
+    }
+}
+
+fn to_csv<T: ToString>(values: &[T]) -> String {
+    values
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+fn parse_csv(csv: &str) -> Result<Vec<u32>, Box<dyn Error>> {
+    Ok(csv
+        .split(',')
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .map(str::parse)
+        .collect::<Result<_, _>>()?)
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+pub fn export_tiled(
+    painter: &TilePainter,
+    region: TileRegion,
+    path: &Path,
+) -> Result<(), Box<dyn Error>> {
+    let format = TiledFormat::from_path(path).ok_or("Tiled maps need a .tmx or .tmj extension")?;
+    let painter = TilePainter {
+        view_mode: ViewMode::TileTypes,
+        ..*painter
+    };
+
+    let tilesets = TiledTilesets {
+        terrain: TiledImage::new(&export::tileset_image_path(painter.tileset), path)?,
+        columns: painter.tileset.columns,
+        decorations: Decoration::ALL
+            .iter()
+            .map(|decoration| TiledImage::new(&export::decoration_image_path(*decoration), path))
+            .collect::<Result<_, _>>()?,
+    };
+
+    let tile_count = (region.size.x * region.size.y) as usize;
+    let mut map = TiledMap {
+        region,
+        seed: painter.noise.seed_text().to_string(),
+        noise_values: painter.noise.values,
+        terrain: vec![0; tile_count],
+        decorations: vec![0; tile_count],
+        first_decoration_gid: tilesets.first_decoration_gid(),
+        heights: vec![0.0; tile_count],
+        humidities: vec![0.0; tile_count],
+    };
+    export::for_each_tile(&painter, region, |world_tile_pos, samples| {
+        let index = map.index(world_tile_pos);
+        let tile_data = samples.tiles.get(world_tile_pos);
+        // Global tile ids start at 1, 0 means there is no tile.
+        map.terrain[index] = painter.paint(samples, world_tile_pos).0 .0 + 1;
+        map.decorations[index] = map.decoration_gid(tile_data.decoration);
+        map.heights[index] = tile_data.height;
+        map.humidities[index] = tile_data.humidity;
+    });
+
+    let content = match format {
+        TiledFormat::Tmx => write_tmx(&map, &tilesets),
+        TiledFormat::Tmj => serde_json::to_string_pretty(&write_tmj(&map, &tilesets))?,
+    };
+    std::fs::write(path, content)?;
+    Ok(())
+}
+
+// Tiled resolves the tileset image relative to the map, so the map keeps working when both are
+// moved together or opened on another machine.
+fn image_source(image_path: &Path, map_path: &Path) -> Result<String, Box<dyn Error>> {
+    let image_path = image_path.canonicalize()?;
+    let map_dir = match map_path.parent() {
+        Some(parent) if parent != Path::new("") => parent.canonicalize()?,
+        _ => std::env::current_dir()?,
+    };
+
+    let image_components: Vec<Component> = image_path.components().collect();
+    let map_components: Vec<Component> = map_dir.components().collect();
+    let common = image_components
+        .iter()
+        .zip(&map_components)
+        .take_while(|(a, b)| a == b)
+        .count();
+    // Different drives on Windows, there is no relative path.
+    if common == 0 {
+        return Ok(image_path.to_string_lossy().into_owned());
+    }
+
+    let parts: Vec<String> = std::iter::repeat("..".to_string())
+        .take(map_components.len() - common)
+        .chain(
+            image_components[common..]
+                .iter()
+                .map(|component| component.as_os_str().to_string_lossy().into_owned()),
+        )
+        .collect();
+    Ok(parts.join("/"))
+}
+
+struct TiledImage {
+    source: String,
+    size: UVec2,
+}
+
+impl TiledImage {
+    fn new(image_path: &Path, map_path: &Path) -> Result<Self, Box<dyn Error>> {
+        let (width, height) = image::image_dimensions(image_path)?;
+        Ok(TiledImage {
+            source: image_source(image_path, map_path)?,
+            size: UVec2::new(width, height),
+        })
+    }
+}
+
+// The terrain atlas, followed by an image collection with one tile per decoration.
+struct TiledTilesets {
+    terrain: TiledImage,
+    columns: u32,
+    decorations: Vec<TiledImage>,
+}
+
+impl TiledTilesets {
+    fn terrain_tile_count(&self) -> u32 {
+        self.columns * (self.terrain.size.y / TILE_SIZE.y as u32)
+    }
+
+    fn first_decoration_gid(&self) -> u32 {
+        1 + self.terrain_tile_count()
+    }
+
+    // Image collections use the size of their largest image.
+    fn decoration_tile_size(&self) -> UVec2 {
+        self.decorations
+            .iter()
+            .fold(UVec2::ZERO, |size, image| size.max(image.size))
+    }
+}
+
+fn write_tmx(map: &TiledMap, tilesets: &TiledTilesets) -> String {
+    let width = map.region.size.x;
+    let height = map.region.size.y;
+    let data = |tiles: &[u32]| {
+        tiles
+            .chunks(width as usize)
+            .map(to_csv)
+            .collect::<Vec<_>>()
+            .join(",\n")
+    };
+
+    let mut tmx = String::new();
+    let _ = writeln!(tmx, r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+    let _ = writeln!(
+        tmx,
+        r#"<map version="1.10" orientation="orthogonal" renderorder="right-down" width="{width}" height="{height}" tilewidth="{}" tileheight="{}" infinite="0" nextlayerid="3" nextobjectid="1">"#,
+        TILE_SIZE.x, TILE_SIZE.y
+    );
+    let _ = writeln!(tmx, " <properties>");
+    let _ = writeln!(
+        tmx,
+        r#"  <property name="seed" value="{}"/>"#,
+        escape_xml(&map.seed)
+    );
+    let _ = writeln!(
+        tmx,
+        r#"  <property name="resolution" type="float" value="{}"/>"#,
+        map.noise_values.resolution
+    );
+    let _ = writeln!(
+        tmx,
+        r#"  <property name="height_offset" type="float" value="{}"/>"#,
+        map.noise_values.height_offset
+    );
+    let _ = writeln!(
+        tmx,
+        r#"  <property name="humidity_offset" type="float" value="{}"/>"#,
+        map.noise_values.humidity_offset
+    );
+    let _ = writeln!(
+        tmx,
+        r#"  <property name="origin_x" type="int" value="{}"/>"#,
+        map.region.origin.x
+    );
+    let _ = writeln!(
+        tmx,
+        r#"  <property name="origin_y" type="int" value="{}"/>"#,
+        map.region.origin.y
+    );
+    let _ = writeln!(tmx, " </properties>");
+    let _ = writeln!(
+        tmx,
+        r#" <tileset firstgid="1" name="{TERRAIN_TILESET}" tilewidth="{}" tileheight="{}" tilecount="{}" columns="{}">"#,
+        TILE_SIZE.x,
+        TILE_SIZE.y,
+        tilesets.terrain_tile_count(),
+        tilesets.columns
+    );
+    let _ = writeln!(
+        tmx,
+        r#"  <image source="{}" width="{}" height="{}"/>"#,
+        escape_xml(&tilesets.terrain.source),
+        tilesets.terrain.size.x,
+        tilesets.terrain.size.y
+    );
+    let _ = writeln!(tmx, " </tileset>");
+    let decoration_tile_size = tilesets.decoration_tile_size();
+    let _ = writeln!(
+        tmx,
+        r#" <tileset firstgid="{}" name="{DECORATION_TILESET}" tilewidth="{}" tileheight="{}" tilecount="{}" columns="0">"#,
+        map.first_decoration_gid,
+        decoration_tile_size.x,
+        decoration_tile_size.y,
+        tilesets.decorations.len()
+    );
+    let _ = writeln!(
+        tmx,
+        r#"  <grid orientation="orthogonal" width="1" height="1"/>"#
+    );
+    for (id, image) in tilesets.decorations.iter().enumerate() {
+        let _ = writeln!(tmx, r#"  <tile id="{id}">"#);
+        let _ = writeln!(
+            tmx,
+            r#"   <image source="{}" width="{}" height="{}"/>"#,
+            escape_xml(&image.source),
+            image.size.x,
+            image.size.y
+        );
+        let _ = writeln!(tmx, "  </tile>");
+    }
+    let _ = writeln!(tmx, " </tileset>");
+    let _ = writeln!(
+        tmx,
+        r#" <layer id="1" name="{TERRAIN_LAYER}" width="{width}" height="{height}">"#
+    );
+    let _ = writeln!(tmx, "  <properties>");
+    let _ = writeln!(
+        tmx,
+        r#"   <property name="height" value="{}"/>"#,
+        to_csv(&map.heights)
+    );
+    let _ = writeln!(
+        tmx,
+        r#"   <property name="humidity" value="{}"/>"#,
+        to_csv(&map.humidities)
+    );
+    let _ = writeln!(tmx, "  </properties>");
+    let _ = writeln!(tmx, r#"  <data encoding="csv">"#);
+    let _ = writeln!(tmx, "{}", data(&map.terrain));
+    let _ = writeln!(tmx, "</data>");
+    let _ = writeln!(tmx, " </layer>");
+    let _ = writeln!(
+        tmx,
+        r#" <layer id="2" name="{DECORATION_LAYER}" width="{width}" height="{height}">"#
+    );
+    let _ = writeln!(tmx, r#"  <data encoding="csv">"#);
+    let _ = writeln!(tmx, "{}", data(&map.decorations));
+    let _ = writeln!(tmx, "</data>");
+    let _ = writeln!(tmx, " </layer>");
+    let _ = writeln!(tmx, "</map>");
+    tmx
+}
+
+fn write_tmj(map: &TiledMap, tilesets: &TiledTilesets) -> Value {
+    let width = map.region.size.x;
+    let height = map.region.size.y;
+    let decoration_tile_size = tilesets.decoration_tile_size();
+    let decoration_tiles: Vec<Value> = tilesets
+        .decorations
+        .iter()
+        .enumerate()
+        .map(|(id, image)| {
+            json!({
+                "id": id,
+                "image": image.source,
+                "imagewidth": image.size.x,
+                "imageheight": image.size.y,
+            })
+        })
+        .collect();
+    json!({
+        "type": "map",
+        "version": "1.10",
+        "orientation": "orthogonal",
+        "renderorder": "right-down",
+        "width": width,
+        "height": height,
+        "tilewidth": TILE_SIZE.x,
+        "tileheight": TILE_SIZE.y,
+        "infinite": false,
+        "nextlayerid": 3,
+        "nextobjectid": 1,
+        "properties": [
+            { "name": "seed", "type": "string", "value": map.seed },
+            { "name": "resolution", "type": "float", "value": map.noise_values.resolution },
+            { "name": "height_offset", "type": "float", "value": map.noise_values.height_offset },
+            { "name": "humidity_offset", "type": "float", "value": map.noise_values.humidity_offset },
+            { "name": "origin_x", "type": "int", "value": map.region.origin.x },
+            { "name": "origin_y", "type": "int", "value": map.region.origin.y },
+        ],
+        "tilesets": [
+            {
+                "firstgid": 1,
+                "name": TERRAIN_TILESET,
+                "image": tilesets.terrain.source,
+                "imagewidth": tilesets.terrain.size.x,
+                "imageheight": tilesets.terrain.size.y,
+                "tilewidth": TILE_SIZE.x,
+                "tileheight": TILE_SIZE.y,
+                "tilecount": tilesets.terrain_tile_count(),
+                "columns": tilesets.columns,
+                "margin": 0,
+                "spacing": 0,
+            },
+            {
+                "firstgid": map.first_decoration_gid,
+                "name": DECORATION_TILESET,
+                "tilewidth": decoration_tile_size.x,
+                "tileheight": decoration_tile_size.y,
+                "tilecount": tilesets.decorations.len(),
+                "columns": 0,
+                "margin": 0,
+                "spacing": 0,
+                "grid": { "orientation": "orthogonal", "width": 1, "height": 1 },
+                "tiles": decoration_tiles,
+            },
+        ],
+        "layers": [
+            {
+                "id": 1,
+                "type": "tilelayer",
+                "name": TERRAIN_LAYER,
+                "x": 0,
+                "y": 0,
+                "width": width,
+                "height": height,
+                "opacity": 1,
+                "visible": true,
+                "data": map.terrain,
+                "properties": [
+                    { "name": "height", "type": "string", "value": to_csv(&map.heights) },
+                    { "name": "humidity", "type": "string", "value": to_csv(&map.humidities) },
+                ],
+            },
+            {
+                "id": 2,
+                "type": "tilelayer",
+                "name": DECORATION_LAYER,
+                "x": 0,
+                "y": 0,
+                "width": width,
+                "height": height,
+                "opacity": 1,
+                "visible": true,
+                "data": map.decorations,
+            },
+        ],
+    })
+}
+
+// Stores every tile of the map whose type or decoration differs from the current map, the
+// generator with the deltas applied, as a delta. Returns how many tiles changed. The map has to
+// come from the same seed and noise values, otherwise every tile would turn into an edit.
+pub fn import_tiled(
+    path: &Path,
+    tileset: &Tileset,
+    noise: &NoiseGenerator,
+    deltas: &mut TileDeltas,
+) -> Result<usize, Box<dyn Error>> {
+    let format = TiledFormat::from_path(path).ok_or("Tiled maps need a .tmx or .tmj extension")?;
+    let content = std::fs::read_to_string(path)?;
+    let map = match format {
+        TiledFormat::Tmx => read_tmx(&content)?,
+        TiledFormat::Tmj => read_tmj(&content)?,
+    };
+    let tile_count = (map.region.size.x * map.region.size.y) as usize;
+    if map.terrain.len() != tile_count || map.decorations.len() != tile_count {
+        return Err("layers do not match the map size".into());
+    }
+    if map.seed != noise.seed_text() || map.noise_values != noise.values {
+        return Err(format!(
+            "map is from seed '{}' with other settings, load world code {} before importing",
+            map.seed,
+            world_code::encode(&map.seed, &map.noise_values)
+        )
+        .into());
+    }
+
+    let mut tiles = noise.get_tile_data_grid(map.region.origin, map.region.size);
+    deltas.apply(&mut tiles);
+    let mut changed = 0;
+    for index in 0..tile_count {
+        let world_tile_pos = map.world_tile_pos(index);
+        let current = tiles.get(world_tile_pos);
+        let tile_type = match map.terrain[index] & !GID_FLAGS {
+            0 => None,
+            gid => tileset
+                .tile_type_of(TileTextureIndex(gid - 1))
+                .filter(|tile_type| *tile_type != current.get_tile_type()),
+        };
+        let decoration = map.decoration(map.decorations[index]);
+        if tile_type.is_none() && decoration == current.decoration {
+            continue;
+        }
+
+        changed += 1;
+        deltas.modify(world_tile_pos, |delta| {
+            if tile_type.is_some() {
+                delta.tile_type = tile_type;
+            }
+            // The generator doesn't place decorations, so a tile without one in the delta has none.
+            delta.decoration = decoration;
+        });
+    }
+    Ok(changed)
+}
+
+fn read_tmx(content: &str) -> Result<TiledMap, Box<dyn Error>> {
+    let document = roxmltree::Document::parse(content)?;
+    let map = document.root_element();
+    let attribute = |node: roxmltree::Node, name: &str| -> Result<u32, Box<dyn Error>> {
+        Ok(node
+            .attribute(name)
+            .ok_or(format!("map is missing '{name}'"))?
+            .parse()?)
+    };
+    let property = |name: &str| -> Result<&str, Box<dyn Error>> {
+        map.children()
+            .filter(|node| node.has_tag_name("properties"))
+            .flat_map(|node| node.children())
+            .find(|node| node.has_tag_name("property") && node.attribute("name") == Some(name))
+            .and_then(|node| node.attribute("value"))
+            .ok_or_else(|| format!("map is missing the '{name}' property").into())
+    };
+    let layer_data = |name: &str| -> Result<Vec<u32>, Box<dyn Error>> {
+        let layer = map
+            .children()
+            .find(|node| node.has_tag_name("layer") && node.attribute("name") == Some(name))
+            .ok_or(format!("map has no '{name}' layer"))?;
+        let data = layer
+            .children()
+            .find(|node| node.has_tag_name("data"))
+            .ok_or(format!("{name} layer has no data"))?;
+        if data.attribute("encoding") != Some("csv") || data.attribute("compression").is_some() {
+            return Err("only uncompressed CSV layer data is supported".into());
+        }
+        parse_csv(data.text().unwrap_or_default())
+    };
+    let decoration_tileset = map
+        .children()
+        .find(|node| {
+            node.has_tag_name("tileset") && node.attribute("name") == Some(DECORATION_TILESET)
+        })
+        .ok_or(format!("map has no '{DECORATION_TILESET}' tileset"))?;
+
+    Ok(TiledMap {
+        region: TileRegion {
+            origin: IVec2::new(
+                property("origin_x")?.parse()?,
+                property("origin_y")?.parse()?,
+            ),
+            size: UVec2::new(attribute(map, "width")?, attribute(map, "height")?),
+        },
+        seed: property("seed")?.to_string(),
+        noise_values: NoiseValues {
+            resolution: property("resolution")?.parse()?,
+            height_offset: property("height_offset")?.parse()?,
+            humidity_offset: property("humidity_offset")?.parse()?,
+        },
+        terrain: layer_data(TERRAIN_LAYER)?,
+        decorations: layer_data(DECORATION_LAYER)?,
+        first_decoration_gid: attribute(decoration_tileset, "firstgid")?,
+        heights: Vec::new(),
+        humidities: Vec::new(),
+    })
+}
+
+fn read_tmj(content: &str) -> Result<TiledMap, Box<dyn Error>> {
+    let map: Value = serde_json::from_str(content)?;
+    let size = |name: &str| -> Result<u32, Box<dyn Error>> {
+        Ok(map[name]
+            .as_u64()
+            .ok_or(format!("map is missing '{name}'"))? as u32)
+    };
+    let property = |name: &str| -> Result<&Value, Box<dyn Error>> {
+        map["properties"]
+            .as_array()
+            .and_then(|properties| properties.iter().find(|property| property["name"] == name))
+            .map(|property| &property["value"])
+            .ok_or_else(|| format!("map is missing the '{name}' property").into())
+    };
+    let integer = |name: &str| -> Result<i32, Box<dyn Error>> {
+        Ok(property(name)?
+            .as_i64()
+            .ok_or(format!("'{name}' is not a number"))? as i32)
+    };
+    let float = |name: &str| -> Result<f64, Box<dyn Error>> {
+        Ok(property(name)?
+            .as_f64()
+            .ok_or(format!("'{name}' is not a number"))?)
+    };
+    let layer_data = |name: &str| -> Result<Vec<u32>, Box<dyn Error>> {
+        let layer = map["layers"]
+            .as_array()
+            .and_then(|layers| layers.iter().find(|layer| layer["name"] == name))
+            .ok_or(format!("map has no '{name}' layer"))?;
+        Ok(layer["data"]
+            .as_array()
+            .ok_or("only uncompressed CSV layer data is supported")?
+            .iter()
+            .map(|gid| gid.as_u64().map(|gid| gid as u32))
+            .collect::<Option<_>>()
+            .ok_or(format!("{name} layer contains invalid tile ids"))?)
+    };
+    let first_decoration_gid = map["tilesets"]
+        .as_array()
+        .and_then(|tilesets| {
+            tilesets
+                .iter()
+                .find(|tileset| tileset["name"] == DECORATION_TILESET)
+        })
+        .and_then(|tileset| tileset["firstgid"].as_u64())
+        .ok_or(format!("map has no '{DECORATION_TILESET}' tileset"))?;
+
+    Ok(TiledMap {
+        region: TileRegion {
+            origin: IVec2::new(integer("origin_x")?, integer("origin_y")?),
+            size: UVec2::new(size("width")?, size("height")?),
+        },
+        seed: property("seed")?
+            .as_str()
+            .ok_or("'seed' is not a string")?
+            .to_string(),
+        noise_values: NoiseValues {
+            resolution: float("resolution")?,
+            height_offset: float("height_offset")? as f32,
+            humidity_offset: float("humidity_offset")? as f32,
+        },
+        terrain: layer_data(TERRAIN_LAYER)?,
+        decorations: layer_data(DECORATION_LAYER)?,
+        first_decoration_gid: first_decoration_gid as u32,
+        heights: Vec::new(),
+        humidities: Vec::new(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_map::TileRenderSettings;
+    use crate::tile_data::TileType;
+
+    fn export(noise: &NoiseGenerator, deltas: &TileDeltas, region: TileRegion, path: &Path) {
+        let tileset = Tileset::default();
+        let render_settings = TileRenderSettings::default();
+        let painter = TilePainter {
+            noise,
+            tileset: &tileset,
+            render_settings: &render_settings,
+            deltas,
+            view_mode: ViewMode::TileTypes,
+        };
+        export_tiled(&painter, region, path).unwrap();
+    }
+
+    #[test]
+    fn edits_round_trip() {
+        let noise = NoiseGenerator::new(
+            &"<tiled & \"round\" trip>".to_string(),
+            NoiseValues {
+                height_offset: 0.1,
+                humidity_offset: -0.2,
+                ..default()
+            },
+        );
+        let region = TileRegion {
+            origin: IVec2::new(-5, -3),
+            size: UVec2::new(8, 6),
+        };
+        let tree = IVec2::new(-2, 0);
+        let other_tree = IVec2::new(1, -2);
+        let stone = (-5..3)
+            .map(|x| IVec2::new(x, 2))
+            .find(|pos| noise.get_tile_data(*pos).get_tile_type() != TileType::Stone)
+            .unwrap();
+        let mut deltas = TileDeltas::default();
+        deltas.modify(tree, |delta| delta.decoration = Some(Decoration::Tree));
+        deltas.set_tile_type(stone, TileType::Stone);
+
+        for extension in ["tmx", "tmj"] {
+            let path = std::env::temp_dir().join(format!(
+                "procjam_tiled_round_trip_{}.{extension}",
+                std::process::id()
+            ));
+            export(&noise, &deltas, region, &path);
+            let tileset = Tileset::default();
+
+            let mut unchanged = deltas.clone();
+            assert_eq!(
+                import_tiled(&path, &tileset, &noise, &mut unchanged).unwrap(),
+                0
+            );
+
+            let mut imported = TileDeltas::default();
+            assert_eq!(
+                import_tiled(&path, &tileset, &noise, &mut imported).unwrap(),
+                2
+            );
+            assert_eq!(imported.get(tree).decoration, Some(Decoration::Tree));
+            assert_eq!(imported.get(stone).tile_type, Some(TileType::Stone));
+
+            // Trees that were removed in Tiled are removed from the world as well.
+            let mut removed = deltas.clone();
+            removed.modify(other_tree, |delta| {
+                delta.decoration = Some(Decoration::Tree)
+            });
+            assert_eq!(
+                import_tiled(&path, &tileset, &noise, &mut removed).unwrap(),
+                1
+            );
+            assert_eq!(removed.get(other_tree).decoration, None);
+
+            let other_noise = NoiseGenerator::new(
+                &noise.seed_text().to_string(),
+                NoiseValues {
+                    height_offset: 0.2,
+                    ..noise.values
+                },
+            );
+            assert!(import_tiled(&path, &tileset, &other_noise, &mut imported).is_err());
+            let _ = std::fs::remove_file(&path);
+        }
+    }
+}
@@ -0,0 +1,141 @@
+use crate::game_map::ChunkSamples;
+use crate::tile_data::TileGrid;
+use bevy::prelude::*;
+use std::cmp::Ordering;
+
+// River flow is accumulated over this many tiles around a chunk, so streams entering it from
+// outside are still visible.
+pub const RIVER_FLOW_MARGIN: u32 = 32;
+
+const SLOPE_SCALE: f32 = 10.0;
+
+const HEATMAP: [Color; 5] = [
+    Color::rgb(0.15, 0.15, 0.7),
+    Color::rgb(0.1, 0.7, 0.9),
+    Color::rgb(0.2, 0.8, 0.2),
+    Color::rgb(0.95, 0.85, 0.1),
+    Color::rgb(0.85, 0.1, 0.1),
+];
+
+#[derive(Resource, Default, Copy, Clone, PartialEq, Eq, Debug)]
+pub enum ViewMode {
+    #[default]
+    TileTypes,
+    Height,
+    Humidity,
+    BiomeWeight,
+    Temperature,
+    Slope,
+    RiverFlow,
+}
+
+impl ViewMode {
+    pub const ALL: [ViewMode; 7] = [
+        ViewMode::TileTypes,
+        ViewMode::Height,
+        ViewMode::Humidity,
+        ViewMode::BiomeWeight,
+        ViewMode::Temperature,
+        ViewMode::Slope,
+        ViewMode::RiverFlow,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ViewMode::TileTypes => "Tile Types",
+            ViewMode::Height => "Height",
+            ViewMode::Humidity => "Humidity",
+            ViewMode::BiomeWeight => "Biome Weight",
+            ViewMode::Temperature => "Temperature",
+            ViewMode::Slope => "Slope",
+            ViewMode::RiverFlow => "River Flow",
+        }
+    }
+
+    pub fn legend(&self) -> &'static str {
+        match self {
+            ViewMode::TileTypes => "",
+            ViewMode::Height => "low (-1) to high (1)",
+            ViewMode::Humidity => "wet (-1) to dry (1)",
+            ViewMode::BiomeWeight => "wet/dry biome (0) to regular biome (1)",
+            ViewMode::Temperature => "cold to hot, derived from height and humidity",
+            ViewMode::Slope => "flat to steep",
+            ViewMode::RiverFlow => "no upstream tiles to many, logarithmic",
+        }
+    }
+
+    pub fn sample_padding(&self) -> u32 {
+        match self {
+            ViewMode::RiverFlow => RIVER_FLOW_MARGIN,
+            _ => 1,
+        }
+    }
+
+    // Normalized to 0..1, or None if this mode shows the regular tiles.
+    pub fn value(&self, samples: &ChunkSamples, world_tile_pos: IVec2) -> Option<f32> {
+        let tile_data = samples.tiles.get(world_tile_pos);
+        let value = match self {
+            ViewMode::TileTypes => return None,
+            ViewMode::Height => (tile_data.height + 1.0) * 0.5,
+            ViewMode::Humidity => (tile_data.humidity + 1.0) * 0.5,
+            ViewMode::BiomeWeight => tile_data.get_biome_weight(),
+            ViewMode::Temperature => tile_data.get_temperature(),
+            ViewMode::Slope => samples.tiles.height_gradient(world_tile_pos).length() * SLOPE_SCALE,
+            ViewMode::RiverFlow => *samples.river_flow.as_ref()?.get(world_tile_pos),
+        };
+        Some(value.clamp(0.0, 1.0))
+    }
+}
+
+pub fn heatmap(value: f32) -> Color {
+    let scaled = value.clamp(0.0, 1.0) * (HEATMAP.len() - 1) as f32;
+    let index = (scaled as usize).min(HEATMAP.len() - 2);
+    let t = scaled - index as f32;
+    let [r1, g1, b1, _] = HEATMAP[index].as_rgba_f32();
+    let [r2, g2, b2, _] = HEATMAP[index + 1].as_rgba_f32();
+    Color::rgb(r1 + (r2 - r1) * t, g1 + (g2 - g1) * t, b1 + (b2 - b1) * t)
+}
+
+// D8 flow accumulation: every tile drains into its lowest lower neighbour, so each tile ends up
+// with the number of tiles upstream of it. Only tiles inside the grid are considered.
+pub fn river_flow(height: &TileGrid<f32>) -> TileGrid<f32> {
+    let positions: Vec<IVec2> = height.positions().collect();
+    let width = height.size().x as i32;
+    let index = |pos: IVec2| {
+        let local = pos - height.origin();
+        (local.y * width + local.x) as usize
+    };
+
+    let heights = height.values();
+    let mut order: Vec<usize> = (0..positions.len()).collect();
+    order.sort_by(|a, b| {
+        heights[*b]
+            .partial_cmp(&heights[*a])
+            .unwrap_or(Ordering::Equal)
+    });
+
+    let mut flow = vec![1.0f32; positions.len()];
+    for i in order {
+        let pos = positions[i];
+        let mut lowest: Option<usize> = None;
+        for y in -1..=1 {
+            for x in -1..=1 {
+                let neighbour = pos + IVec2::new(x, y);
+                if neighbour == pos || !height.contains(neighbour) {
+                    continue;
+                }
+                let j = index(neighbour);
+                if heights[j] < lowest.map_or(heights[i], |lowest| heights[lowest]) {
+                    lowest = Some(j);
+                }
+            }
+        }
+        if let Some(j) = lowest {
+            flow[j] += flow[i];
+        }
+    }
+
+    let max = (positions.len() as f32).ln();
+    let values = flow.iter().map(|flow| flow.ln() / max).collect();
+    TileGrid::new(height.origin(), height.size(), values)
+}
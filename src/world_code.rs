@@ -0,0 +1,197 @@
+use crate::noise_generator::{
+    NoiseValues, HEIGHT_OFFSET_RANGE, HUMIDITY_OFFSET_RANGE, RESOLUTION_RANGE,
+};
+use crate::preset::GeneratorPreset;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use std::error::Error;
+use std::fmt::Display;
+use std::ops::RangeInclusive;
+
+// A world code is URL safe base64 of:
+//   version (u8), resolution (f64), height_offset (f32), humidity_offset (f32), seed (UTF-8)
+// with all numbers little-endian. Bump the version whenever the layout changes or the generator
+// produces different worlds for the same values, so old codes are rejected instead of silently
+// opening another world.
+const WORLD_CODE_VERSION: u8 = 1;
+const HEADER_LEN: usize = 1 + 8 + 4 + 4;
+
+pub fn encode(seed: &str, values: &NoiseValues) -> String {
+    let mut bytes = Vec::with_capacity(HEADER_LEN + seed.len());
+    bytes.push(WORLD_CODE_VERSION);
+    bytes.extend_from_slice(&values.resolution.to_le_bytes());
+    bytes.extend_from_slice(&values.height_offset.to_le_bytes());
+    bytes.extend_from_slice(&values.humidity_offset.to_le_bytes());
+    bytes.extend_from_slice(seed.as_bytes());
+    URL_SAFE_NO_PAD.encode(bytes)
+}
+
+pub fn decode(code: &str) -> Result<GeneratorPreset, Box<dyn Error>> {
+    let bytes = URL_SAFE_NO_PAD.decode(code.trim())?;
+    if bytes.len() < HEADER_LEN {
+        return Err("world code is too short".into());
+    }
+    if bytes[0] != WORLD_CODE_VERSION {
+        return Err(format!(
+            "world code is from generator version {}, this is version {WORLD_CODE_VERSION}",
+            bytes[0]
+        )
+        .into());
+    }
+
+    let f64_at = |i: usize| f64::from_le_bytes(bytes[i..i + 8].try_into().unwrap());
+    let f32_at = |i: usize| f32::from_le_bytes(bytes[i..i + 4].try_into().unwrap());
+    let noise_values = NoiseValues {
+        resolution: f64_at(1),
+        height_offset: f32_at(9),
+        humidity_offset: f32_at(13),
+    };
+    // Codes come from links, so anything the settings couldn't have produced is rejected.
+    check_range("resolution", noise_values.resolution, RESOLUTION_RANGE)?;
+    check_range(
+        "height offset",
+        noise_values.height_offset,
+        HEIGHT_OFFSET_RANGE,
+    )?;
+    check_range(
+        "humidity offset",
+        noise_values.humidity_offset,
+        HUMIDITY_OFFSET_RANGE,
+    )?;
+
+    Ok(GeneratorPreset {
+        name: String::from("World Code"),
+        seed: String::from_utf8(bytes[HEADER_LEN..].to_vec())?,
+        noise_values,
+    })
+}
+
+// NaN is never within the range, so it gets rejected as well.
+fn check_range<T: PartialOrd + Display>(
+    name: &str,
+    value: T,
+    range: RangeInclusive<T>,
+) -> Result<(), Box<dyn Error>> {
+    if range.contains(&value) {
+        return Ok(());
+    }
+    Err(format!(
+        "world code has {name} {value}, it has to be within {}..={}",
+        range.start(),
+        range.end()
+    )
+    .into())
+}
+
+// In the browser the page's query parameters take the place of command line flags:
+// ?world=<code>, or ?preset=<name> optionally combined with ?seed=<seed>.
+#[cfg(target_arch = "wasm32")]
+pub fn from_url() -> Result<Option<GeneratorPreset>, Box<dyn Error>> {
+    let Some(search) = web_sys::window().and_then(|window| window.location().search().ok()) else {
+        return Ok(None);
+    };
+    let params =
+        web_sys::UrlSearchParams::new_with_str(&search).map_err(|_| "invalid query parameters")?;
+
+    if let Some(code) = params.get("world") {
+        return decode(&code).map(Some);
+    }
+    let preset_name = params.get("preset");
+    let seed = params.get("seed");
+    if preset_name.is_none() && seed.is_none() {
+        return Ok(None);
+    }
+
+    let mut preset = match preset_name {
+        Some(name) => GeneratorPreset::find(&name)?,
+        None => GeneratorPreset::default(),
+    };
+    if let Some(seed) = seed {
+        preset.seed = seed;
+    }
+    Ok(Some(preset))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn from_url() -> Result<Option<GeneratorPreset>, Box<dyn Error>> {
+    Ok(None)
+}
+
+// Replaces the query of the current page, so its address can be shared right away.
+#[cfg(target_arch = "wasm32")]
+pub fn update_url(code: &str) {
+    let Some(window) = web_sys::window() else {
+        return;
+    };
+    if let Ok(history) = window.history() {
+        let url = format!("?world={code}");
+        let _ = history.replace_state_with_url(&wasm_bindgen::JsValue::NULL, "", Some(&url));
+    }
+}
+
+// Link to the current page with the world code as the only query parameter.
+#[cfg(target_arch = "wasm32")]
+pub fn share_url(code: &str) -> Option<String> {
+    let location = web_sys::window()?.location();
+    let origin = location.origin().ok()?;
+    let path = location.pathname().ok()?;
+    Some(format!("{origin}{path}?world={code}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn code_with_resolution(resolution: f64) -> String {
+        encode(
+            "seed",
+            &NoiseValues {
+                resolution,
+                ..NoiseValues::default()
+            },
+        )
+    }
+
+    #[test]
+    fn codes_round_trip() {
+        let values = NoiseValues {
+            resolution: 0.05,
+            height_offset: -0.25,
+            humidity_offset: 0.5,
+        };
+        let preset = decode(&encode("some seed", &values)).unwrap();
+        assert_eq!(preset.seed, "some seed");
+        assert!(preset.noise_values == values);
+    }
+
+    #[test]
+    fn resolutions_outside_the_settings_are_rejected() {
+        for resolution in [0.0, 0.2, -0.05, f64::NAN, f64::INFINITY] {
+            assert!(decode(&code_with_resolution(resolution)).is_err());
+        }
+        assert!(decode(&code_with_resolution(0.001)).is_ok());
+        assert!(decode(&code_with_resolution(0.1)).is_ok());
+    }
+
+    #[test]
+    fn offsets_outside_the_settings_are_rejected() {
+        let code_with_offsets = |height_offset: f32, humidity_offset: f32| {
+            encode(
+                "seed",
+                &NoiseValues {
+                    height_offset,
+                    humidity_offset,
+                    ..NoiseValues::default()
+                },
+            )
+        };
+        for height_offset in [40.0, -0.6, f32::NAN] {
+            assert!(decode(&code_with_offsets(height_offset, 0.0)).is_err());
+        }
+        for humidity_offset in [1.5, -1.1, f32::INFINITY] {
+            assert!(decode(&code_with_offsets(0.0, humidity_offset)).is_err());
+        }
+        assert!(decode(&code_with_offsets(-0.5, 1.0)).is_ok());
+        assert!(decode(&code_with_offsets(0.5, -1.0)).is_ok());
+    }
+}
@@ -0,0 +1,133 @@
+use crate::game_ui::{RegenerateMapEvent, UnappliedSettings};
+use crate::noise_generator::{NoiseGenerator, NoiseValues};
+use crate::tile_deltas::{ChunkDeltas, TileDeltas};
+use bevy::prelude::*;
+use bevy_egui::egui::Align2;
+use bevy_egui::*;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::path::{Path, PathBuf};
+
+// Saves write straight to the file system, so this isn't available in the browser.
+pub struct WorldSavePlugin;
+impl Plugin for WorldSavePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, world_save_ui_system);
+    }
+}
+
+// Bump this whenever older saves can't be read or would generate a different world anymore.
+const SAVE_VERSION: u32 = 1;
+
+// Everything needed to get back to the same world: the generator settings plus every tile that
+// was changed after generating it.
+#[derive(Serialize, Deserialize)]
+pub struct WorldSave {
+    version: u32,
+    pub seed: String,
+    pub noise_values: NoiseValues,
+    pub chunks: Vec<ChunkDeltas>,
+}
+
+impl WorldSave {
+    pub fn new(noise: &NoiseGenerator, deltas: &TileDeltas) -> Self {
+        WorldSave {
+            version: SAVE_VERSION,
+            seed: noise.seed_text().to_string(),
+            noise_values: noise.values,
+            chunks: deltas.to_chunks(),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        let pretty = ron::ser::PrettyConfig::default();
+        std::fs::write(path, ron::ser::to_string_pretty(self, pretty)?)?;
+        Ok(())
+    }
+
+    pub fn load(path: &Path) -> Result<WorldSave, Box<dyn Error>> {
+        let save: WorldSave = ron::from_str(&std::fs::read_to_string(path)?)?;
+        if save.version != SAVE_VERSION {
+            return Err(format!(
+                "save is from version {}, this is version {SAVE_VERSION}",
+                save.version
+            )
+            .into());
+        }
+        Ok(save)
+    }
+}
+
+struct WorldSaveState {
+    path: String,
+    status: String,
+}
+impl Default for WorldSaveState {
+    fn default() -> Self {
+        WorldSaveState {
+            path: String::from("world.ron"),
+            status: String::new(),
+        }
+    }
+}
+
+fn world_save_ui_system(
+    mut contexts: EguiContexts,
+    mut state: Local<WorldSaveState>,
+    mut noise: ResMut<NoiseGenerator>,
+    mut deltas: ResMut<TileDeltas>,
+    mut unapplied_settings: ResMut<UnappliedSettings>,
+    mut regenerate_map_event: EventWriter<RegenerateMapEvent>,
+) {
+    let mut save = false;
+    let mut load = false;
+    let mut clear = false;
+    egui::Window::new("World")
+        .default_open(false)
+        .resizable(false)
+        .anchor(Align2::CENTER_TOP, egui::Vec2::new(0.0, 5.0))
+        .show(contexts.ctx_mut(), |ui| {
+            ui.horizontal(|ui| {
+                ui.label("File:");
+                ui.text_edit_singleline(&mut state.path);
+            });
+            ui.horizontal(|ui| {
+                save = ui.button("Save").clicked();
+                load = ui.button("Load").clicked();
+                clear = ui
+                    .add_enabled(!deltas.is_empty(), egui::Button::new("Discard Edits"))
+                    .clicked();
+            });
+            ui.label(format!("{} edited tiles", deltas.len()));
+            if !state.status.is_empty() {
+                ui.label(&state.status);
+            }
+        });
+
+    let path = PathBuf::from(&state.path);
+    if save {
+        state.status = match WorldSave::new(&noise, &deltas).save(&path) {
+            Ok(()) => format!("Saved to {}", path.display()),
+            Err(error) => format!("Save failed: {error}"),
+        };
+    }
+
+    if load {
+        match WorldSave::load(&path) {
+            Ok(save) => {
+                unapplied_settings.seed = save.seed.clone();
+                unapplied_settings.map_generator_values = save.noise_values;
+                *noise = NoiseGenerator::new(&save.seed, save.noise_values);
+                *deltas = TileDeltas::from_chunks(&noise, save.chunks);
+                regenerate_map_event.send(RegenerateMapEvent);
+                state.status = format!("Loaded {}", path.display());
+            }
+            Err(error) => state.status = format!("Load failed: {error}"),
+        }
+    }
+
+    if clear {
+        deltas.clear();
+        regenerate_map_event.send(RegenerateMapEvent);
+    }
+}
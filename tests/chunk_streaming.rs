@@ -0,0 +1,150 @@
+use bevy::prelude::*;
+use bevy::render::camera::ScalingMode;
+use procjam_2023::coords;
+use procjam_2023::game_map::{ChunkData, ChunkStreamingSettings, GameMapPlugin, CHUNK_SIZE};
+use procjam_2023::game_ui::RegenerateMapEvent;
+use std::collections::HashSet;
+
+const VIEW_SIZE: Vec2 = Vec2::new(1920.0, 1080.0);
+const DESPAWN_MARGIN: u32 = 2;
+// Chunks are generated on other threads, so this is how long the tests wait for them.
+const MAX_FRAMES: usize = 10_000;
+// Frames to keep running after the expected chunks showed up, to catch chunks arriving late.
+const SETTLE_FRAMES: usize = 10;
+
+fn chunk_world_size() -> Vec2 {
+    CHUNK_SIZE.as_vec2() * coords::tile_size()
+}
+
+// The map plugin without a window or renderer, looking at the given position.
+fn headless_app(camera_pos: Vec2) -> App {
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, AssetPlugin::default()))
+        .init_asset::<Image>()
+        .add_event::<RegenerateMapEvent>()
+        .add_plugins(GameMapPlugin)
+        .insert_resource(ChunkStreamingSettings {
+            frame_budget_ms: 1000.0,
+            despawn_margin: DESPAWN_MARGIN,
+            cache_size: 0,
+        });
+
+    // Normally kept up to date by the render plugin from the window size.
+    let mut camera = Camera2dBundle::default();
+    camera.projection.area = Rect::from_center_size(Vec2::ZERO, VIEW_SIZE);
+    camera.transform.translation = camera_pos.extend(camera.transform.translation.z);
+    app.world.spawn(camera);
+    app
+}
+
+fn spawned_chunks(app: &mut App) -> HashSet<IVec2> {
+    app.world
+        .query::<&ChunkData>()
+        .iter(&app.world)
+        .map(|chunk_data| chunk_data.position)
+        .collect()
+}
+
+fn visible_chunks(camera_pos: Vec2) -> HashSet<IVec2> {
+    let (min, max) = coords::chunks_overlapping(Rect::from_center_size(camera_pos, VIEW_SIZE));
+    (min.y..=max.y)
+        .flat_map(|y| (min.x..=max.x).map(move |x| IVec2::new(x, y)))
+        .collect()
+}
+
+fn move_camera(app: &mut App, camera_pos: Vec2) {
+    let mut transform = app
+        .world
+        .query_filtered::<&mut Transform, With<Camera2d>>()
+        .single_mut(&mut app.world);
+    transform.translation = camera_pos.extend(transform.translation.z);
+}
+
+// Runs frames until the spawned chunks satisfy the condition, then a few more.
+fn update_until(app: &mut App, condition: impl Fn(&HashSet<IVec2>) -> bool) -> HashSet<IVec2> {
+    for _ in 0..MAX_FRAMES {
+        app.update();
+        if condition(&spawned_chunks(app)) {
+            for _ in 0..SETTLE_FRAMES {
+                app.update();
+            }
+            return spawned_chunks(app);
+        }
+    }
+    panic!(
+        "chunks didn't settle within {MAX_FRAMES} frames, spawned: {:?}",
+        spawned_chunks(app)
+    );
+}
+
+#[test]
+fn spawns_the_visible_chunks() {
+    let mut app = headless_app(Vec2::ZERO);
+    let visible = visible_chunks(Vec2::ZERO);
+    let spawned = update_until(&mut app, |spawned| visible.is_subset(spawned));
+    assert_eq!(spawned, visible);
+}
+
+#[test]
+fn spawns_around_negative_positions() {
+    let camera_pos = Vec2::new(-5.3, -11.8) * chunk_world_size();
+    let mut app = headless_app(camera_pos);
+    let visible = visible_chunks(camera_pos);
+    let spawned = update_until(&mut app, |spawned| visible.is_subset(spawned));
+    assert_eq!(spawned, visible);
+}
+
+#[test]
+fn moving_far_replaces_every_chunk() {
+    let mut app = headless_app(Vec2::ZERO);
+    let old = visible_chunks(Vec2::ZERO);
+    update_until(&mut app, |spawned| old.is_subset(spawned));
+
+    let camera_pos = Vec2::new(20.0, 0.0) * chunk_world_size();
+    move_camera(&mut app, camera_pos);
+    let visible = visible_chunks(camera_pos);
+    let spawned = update_until(&mut app, |spawned| {
+        visible.is_subset(spawned) && spawned.is_disjoint(&old)
+    });
+    // Chunks ahead of the camera may have been prefetched while it moved, but nothing outside the
+    // despawn margin is left.
+    let margin = IVec2::splat(DESPAWN_MARGIN as i32);
+    let (min, max) = coords::chunks_overlapping(Rect::from_center_size(camera_pos, VIEW_SIZE));
+    for chunk_pos in spawned {
+        assert!(
+            chunk_pos.cmpge(min - margin).all() && chunk_pos.cmple(max + margin).all(),
+            "{chunk_pos} is out of range"
+        );
+    }
+}
+
+#[test]
+fn chunks_within_the_margin_are_kept() {
+    let mut app = headless_app(Vec2::ZERO);
+    let old = visible_chunks(Vec2::ZERO);
+    update_until(&mut app, |spawned| old.is_subset(spawned));
+
+    let camera_pos = Vec2::new(1.0, 0.0) * chunk_world_size();
+    move_camera(&mut app, camera_pos);
+    let visible = visible_chunks(camera_pos);
+    let spawned = update_until(&mut app, |spawned| visible.is_subset(spawned));
+    assert!(
+        old.is_subset(&spawned),
+        "chunks behind the camera were despawned"
+    );
+}
+
+#[test]
+fn zooming_out_past_the_lod_threshold_despawns_every_chunk() {
+    let mut app = headless_app(Vec2::ZERO);
+    let visible = visible_chunks(Vec2::ZERO);
+    update_until(&mut app, |spawned| visible.is_subset(spawned));
+
+    let mut projection = app
+        .world
+        .query_filtered::<&mut OrthographicProjection, With<Camera2d>>()
+        .single_mut(&mut app.world);
+    projection.scaling_mode = ScalingMode::WindowSize(1.0 / 64.0);
+    let spawned = update_until(&mut app, |spawned| spawned.is_empty());
+    assert!(spawned.is_empty());
+}
@@ -0,0 +1,151 @@
+// Renders fixed regions of a few worlds to one pixel per tile and compares them against the PNGs
+// in tests/golden, so changes to the generator or biome thresholds show up as failing tests.
+//
+// After an intentional change, bless the new output with
+//   BLESS_GOLDENS=1 cargo test --test golden
+// and commit the updated PNGs. New worlds need a golden the same way, a missing one fails.
+
+use bevy::prelude::*;
+use image::{Rgba, RgbaImage};
+use procjam_2023::autotile::Tileset;
+use procjam_2023::export::{self, TileRegion};
+use procjam_2023::game_map::{TilePainter, TileRenderSettings};
+use procjam_2023::noise_generator::NoiseValues;
+use procjam_2023::preset::GeneratorPreset;
+use procjam_2023::tile_deltas::TileDeltas;
+use procjam_2023::view_mode::ViewMode;
+use std::path::{Path, PathBuf};
+
+const BLESS_VAR: &str = "BLESS_GOLDENS";
+// Spans several chunks on both sides of the origin.
+const REGION: TileRegion = TileRegion {
+    origin: IVec2::new(-96, -64),
+    size: UVec2::new(192, 128),
+};
+// Tiles that don't match are drawn in this color in the diff image.
+const DIFF_COLOR: Rgba<u8> = Rgba([255, 0, 255, 255]);
+
+fn golden_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden")
+}
+
+// Goldens that didn't match end up here together with a diff, outside of the source tree.
+fn output_dir() -> PathBuf {
+    Path::new(env!("CARGO_TARGET_TMPDIR")).join("golden")
+}
+
+fn file_name(preset: &GeneratorPreset) -> String {
+    format!("{}.png", preset.name.to_lowercase().replace(' ', "_"))
+}
+
+fn seed(seed: &str) -> GeneratorPreset {
+    GeneratorPreset {
+        name: format!("seed {seed}"),
+        seed: seed.to_string(),
+        noise_values: NoiseValues::default(),
+    }
+}
+
+fn render(preset: &GeneratorPreset) -> RgbaImage {
+    let noise = preset.noise_generator();
+    let tileset = Tileset::default();
+    let render_settings = TileRenderSettings::default();
+    let deltas = TileDeltas::default();
+    let painter = TilePainter {
+        noise: &noise,
+        tileset: &tileset,
+        render_settings: &render_settings,
+        deltas: &deltas,
+        view_mode: ViewMode::TileTypes,
+    };
+    export::render_region(&painter, REGION, None)
+}
+
+// The actual image, dimmed, with every differing pixel highlighted.
+fn diff_image(expected: &RgbaImage, actual: &RgbaImage) -> (RgbaImage, usize) {
+    let mut diff = RgbaImage::new(actual.width(), actual.height());
+    let mut differing = 0;
+    for (x, y, pixel) in actual.enumerate_pixels() {
+        let color = if expected.get_pixel(x, y) == pixel {
+            let [r, g, b, _] = pixel.0;
+            Rgba([r / 3, g / 3, b / 3, 255])
+        } else {
+            differing += 1;
+            DIFF_COLOR
+        };
+        diff.put_pixel(x, y, color);
+    }
+    (diff, differing)
+}
+
+fn check(preset: &GeneratorPreset) -> Result<(), String> {
+    let actual = render(preset);
+    let golden_path = golden_dir().join(file_name(preset));
+    if std::env::var_os(BLESS_VAR).is_some() {
+        std::fs::create_dir_all(golden_dir()).map_err(|error| error.to_string())?;
+        actual
+            .save(&golden_path)
+            .map_err(|error| format!("{}: {error}", golden_path.display()))?;
+        println!("Recorded {}", golden_path.display());
+        return Ok(());
+    }
+    if !golden_path.exists() {
+        return Err(format!(
+            "{}: no golden at {}, run with {BLESS_VAR}=1 to record it",
+            preset.name,
+            golden_path.display()
+        ));
+    }
+
+    let expected = image::open(&golden_path)
+        .map_err(|error| format!("{}: {error}", golden_path.display()))?
+        .into_rgba8();
+    if expected.dimensions() != actual.dimensions() {
+        return Err(format!(
+            "{}: golden is {:?}, rendered {:?}",
+            preset.name,
+            expected.dimensions(),
+            actual.dimensions()
+        ));
+    }
+    let (diff, differing) = diff_image(&expected, &actual);
+    if differing == 0 {
+        return Ok(());
+    }
+
+    let output_dir = output_dir();
+    std::fs::create_dir_all(&output_dir).map_err(|error| error.to_string())?;
+    let stem = file_name(preset).trim_end_matches(".png").to_string();
+    let actual_path = output_dir.join(format!("{stem}.actual.png"));
+    let diff_path = output_dir.join(format!("{stem}.diff.png"));
+    actual
+        .save(&actual_path)
+        .map_err(|error| error.to_string())?;
+    diff.save(&diff_path).map_err(|error| error.to_string())?;
+    Err(format!(
+        "{}: {differing} of {} tiles differ, see {} and {}",
+        preset.name,
+        actual.width() * actual.height(),
+        actual_path.display(),
+        diff_path.display()
+    ))
+}
+
+#[test]
+fn worlds_match_goldens() {
+    let mut presets = GeneratorPreset::built_in();
+    presets.push(GeneratorPreset::default());
+    presets.push(seed("procjam"));
+    presets.push(seed("-7"));
+
+    // Checks every world before failing, so one run shows everything that changed.
+    let failures: Vec<String> = presets
+        .iter()
+        .filter_map(|preset| check(preset).err())
+        .collect();
+    assert!(
+        failures.is_empty(),
+        "{}\nRun with {BLESS_VAR}=1 if this change is intended.",
+        failures.join("\n")
+    );
+}